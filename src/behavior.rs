@@ -0,0 +1,733 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::collision::{AttackId, AttackIds, CanHitTeam, DamageType, Factions, HitBoxEvent, Hurtbox, Team};
+use crate::difficulty::Difficulty;
+use crate::dialogue::{DialogueAction, DialogueEvent};
+use crate::encounter::Enemy;
+use crate::loot::GameRng;
+use crate::pathfinding::{FlowField, NavGrid, Navigator};
+use crate::physics::Velocity;
+use crate::player::Player;
+use crate::projectile::{Projectile, SpawnProjectileEvent};
+use crate::status::{is_stunned, StatusEffects, StatusPayload};
+use crate::telegraph::TelegraphEvent;
+use crate::{Interface, CONTACT_REHIT_FRAMES};
+
+// an enemy's ai, loaded from assets/behavior
+#[derive(Deserialize)]
+pub struct Behavior {
+    pub stagger: u32, // frames it stops thinking for after being hit
+    #[serde(default)]
+    pub provoked_team: Option<Team>, // team it joins when hit or provoked in a conversation
+    pub root: Node,
+    #[serde(default)]
+    pub phases: Vec<Phase>, // later phases, in the order they are entered as health drops
+}
+
+// a boss phase, entered once health falls to `health` of the maximum
+#[derive(Deserialize)]
+pub struct Phase {
+    pub health: f32,
+    pub transition: Node, // played while invulnerable before the phase starts
+    pub root: Node,
+}
+
+// every tree in assets/behavior, keyed by file name
+pub struct BehaviorTrees(pub HashMap<String, Behavior>);
+
+#[derive(Deserialize)]
+pub enum Node {
+    Sequence(Vec<Node>), // runs its children in order until one doesn't succeed
+    Selector(Vec<Node>), // runs its children in order until one doesn't fail
+    Random(Vec<Node>), // runs one of its children, picked at random
+    Condition(Condition),
+    Action(Action),
+}
+
+#[derive(Deserialize)]
+pub enum Condition {
+    Hostile, // its team is hostile to the player
+    PlayerCloserThan(f32),
+    PlayerFurtherThan(f32),
+    AbilityReady(u64), // no ability has been used for this many frames
+    Flag(String),
+    Not(Box<Condition>),
+}
+
+#[derive(Deserialize)]
+pub enum Action {
+    Idle,
+    Chase(f32),
+    Flee(f32),
+    Strafe(f32), // circle around the player
+    Wait(u64),
+    Flash(u64), // blink for this many frames
+    SetFlag(String),
+    ResetCooldown,
+    ContactDamage(HitSpec),
+    Shoot {
+        draw_frames: u64,
+        speed: f32,
+        lifetime: u32,
+        color: (f32, f32, f32),
+        hit: HitSpec,
+    },
+    Blast {
+        windup: u64,
+        active: u64,
+        recovery: u64,
+        distance: f32, // how far towards the player the hitbox is
+        hit: HitSpec,
+    },
+    SpawnMinions {
+        at_frame: u64,
+        duration: u64,
+        shake: f32,
+        minions: Vec<(Enemy, f32)>, // and how far to the side of the summoner they appear
+    },
+}
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum Targets {
+    Hostile,
+    NotAllied,
+    All,
+}
+
+impl Default for Targets {
+    fn default() -> Self {
+        Targets::Hostile
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct HitSpec {
+    pub size: (f32, f32),
+    pub damage: u64,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub knockback: f32,
+    #[serde(default)]
+    pub targets: Targets,
+    #[serde(default)]
+    pub effects: Vec<StatusPayload>,
+}
+
+impl HitSpec {
+    fn size(&self) -> Vec2 {
+        Vec2::new(self.size.0, self.size.1)
+    }
+
+    fn can_hit(&self, team: Team) -> CanHitTeam {
+        match self.targets {
+            Targets::Hostile => CanHitTeam::Hostile(team),
+            Targets::NotAllied => CanHitTeam::NotAllied(team),
+            Targets::All => CanHitTeam::All,
+        }
+    }
+}
+
+pub struct Brain {
+    pub tree: String,
+    pub color: Color, // sprite colour when it isn't winding up an attack
+    running: Vec<usize>, // child indices leading to the action that is still running
+    frame: u64, // frames the current action has been running for
+    age: u64,
+    since_ability: u64,
+    stagger: u32,
+    flags: Vec<String>,
+    attack: AttackId,
+    phase: usize, // 0 until the first of the behavior's phases is entered
+    transitioning: bool,
+    provoked: bool, // provoked in a conversation, joins `provoked_team` once the game unpauses
+    difficulty: Difficulty, // the one it was spawned with
+}
+
+impl Brain {
+    pub fn new(tree: &str, color: Color, difficulty: &Difficulty) -> Self {
+        Brain {
+            tree: tree.to_string(),
+            color,
+            running: vec![],
+            frame: 0,
+            age: 0,
+            since_ability: 0,
+            stagger: 0,
+            flags: vec![],
+            attack: AttackId::default(),
+            phase: 0,
+            transitioning: false,
+            provoked: false,
+            difficulty: difficulty.clone(),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+struct Context<'a> {
+    entity: Entity,
+    position: Vec2,
+    target: Vec2,
+    brain: &'a mut Brain,
+    hurtbox: &'a mut Hurtbox,
+    velocity: &'a mut Velocity,
+    navigator: Option<&'a mut Navigator>,
+    flow: Option<(&'a NavGrid, &'a FlowField)>,
+    factions: &'a Factions,
+    rng: &'a mut GameRng,
+    attack_ids: &'a mut AttackIds,
+    path: Vec<usize>,
+    tint: Option<f32>, // how far into a windup it is, from 0 to 1
+    hitboxes: Vec<HitBoxEvent>,
+    projectiles: Vec<SpawnProjectileEvent>,
+    telegraphs: Vec<TelegraphEvent>,
+    minions: Vec<(Enemy, Vec2)>,
+}
+
+impl<'a> Context<'a> {
+    fn direction(&self) -> Vec2 {
+        (self.target - self.position).normalize()
+    }
+
+    fn distance(&self) -> f32 {
+        (self.target - self.position).length()
+    }
+
+    fn hitbox(&mut self, position: Vec2, hit: &HitSpec) {
+        self.hitboxes.push(HitBoxEvent {
+            position,
+            size: hit.size(),
+            damage: self.brain.difficulty.damage(hit.damage),
+            damage_type: hit.damage_type,
+            knockback: hit.knockback,
+            can_hit: hit.can_hit(self.hurtbox.team),
+            attack: self.brain.attack,
+            owner: Some(self.entity),
+            target: None,
+            effects: hit.effects.clone(),
+        });
+    }
+
+    fn telegraph(&mut self, position: Vec2, size: Vec2, rotation: f32, frame: u64, windup: u64) {
+        self.telegraphs.push(TelegraphEvent {
+            position,
+            size,
+            rotation,
+            progress: frame as f32 / windup as f32,
+            frames_left: windup - frame,
+        });
+    }
+
+    fn finish_ability(&mut self) -> Status {
+        self.hurtbox.invincible = false;
+        self.brain.since_ability = 0;
+        Status::Success
+    }
+}
+
+impl Condition {
+    fn check(&self, ctx: &Context) -> bool {
+        match self {
+            Condition::Hostile => ctx.factions.is_hostile(ctx.hurtbox.team, Team::Player),
+            Condition::PlayerCloserThan(distance) => ctx.distance() < *distance,
+            Condition::PlayerFurtherThan(distance) => ctx.distance() > *distance,
+            Condition::AbilityReady(frames) => ctx.brain.since_ability > ctx.brain.difficulty.frames(*frames),
+            Condition::Flag(flag) => ctx.brain.flags.contains(flag),
+            Condition::Not(condition) => !condition.check(ctx),
+        }
+    }
+}
+
+impl Action {
+    fn run(&self, ctx: &mut Context) -> Status {
+        let frame = ctx.brain.frame;
+        match self {
+            Action::Idle => Status::Success,
+            Action::Chase(speed) => {
+                let straight = ctx.direction();
+                let position = ctx.position;
+                // enemies with a navigator plan their own path, the rest follow the shared flow field
+                let direction = match (ctx.navigator.as_mut(), ctx.flow) {
+                    (Some(navigator), _) => {
+                        navigator.target = Some(ctx.target);
+                        navigator.heading(position)
+                    }
+                    (None, Some((grid, flow_field))) => flow_field.heading(grid, position),
+                    (None, None) => None,
+                };
+                let direction = direction.unwrap_or(straight);
+                ctx.velocity.0 = direction * ctx.brain.difficulty.speed(*speed);
+                Status::Success
+            }
+            Action::Flee(speed) => {
+                ctx.velocity.0 = ctx.direction() * -ctx.brain.difficulty.speed(*speed);
+                Status::Success
+            }
+            Action::Strafe(speed) => {
+                let direction = ctx.direction();
+                ctx.velocity.0 = Vec2::new(-direction.y, direction.x) * ctx.brain.difficulty.speed(*speed);
+                Status::Success
+            }
+            Action::Wait(frames) => {
+                if frame >= ctx.brain.difficulty.frames(*frames) {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            Action::Flash(frames) => {
+                ctx.tint = Some(if (frame / 6) % 2 == 0 { 1.0 } else { 0.0 });
+                if frame >= *frames {
+                    Status::Success
+                } else {
+                    Status::Running
+                }
+            }
+            Action::SetFlag(flag) => {
+                if !ctx.brain.flags.contains(flag) {
+                    ctx.brain.flags.push(flag.clone());
+                }
+                Status::Success
+            }
+            Action::ResetCooldown => {
+                ctx.brain.since_ability = 0;
+                Status::Success
+            }
+            Action::ContactDamage(hit) => {
+                // brains start out sharing the default id, so the first touch needs its own
+                if ctx.brain.attack == AttackId::default() || ctx.brain.age % CONTACT_REHIT_FRAMES == 0 {
+                    ctx.brain.attack = ctx.attack_ids.next();
+                }
+                ctx.hitbox(ctx.position, hit);
+                Status::Success
+            }
+            Action::Shoot { draw_frames, speed, lifetime, color, hit } => {
+                let direction = ctx.direction();
+                // lean back while drawing the bow
+                ctx.velocity.0 = if frame < 8 {
+                    direction * -1.0
+                } else {
+                    Vec2::new(0.0, 0.0)
+                };
+                ctx.tint = Some(frame as f32 / *draw_frames as f32);
+
+                if frame < *draw_frames {
+                    // the whole flight path of the shot
+                    let length = *speed * *lifetime as f32;
+                    let angle = Vec2::new(1.0, 0.0).angle_between(direction);
+                    let position = ctx.position + direction * (50.0 + length / 2.0);
+                    ctx.telegraph(position, Vec2::new(length, hit.size.1), angle, frame, *draw_frames);
+                    return Status::Running;
+                }
+                ctx.projectiles.push(SpawnProjectileEvent {
+                    position: ctx.position + direction * 50.0,
+                    color: Color::rgb(color.0, color.1, color.2),
+                    vel: direction * *speed,
+                    projectile: Projectile {
+                        size: hit.size(),
+                        lifetime: *lifetime,
+                        pierce: 0,
+                        damage: ctx.brain.difficulty.damage(hit.damage),
+                        damage_type: hit.damage_type,
+                        knockback: hit.knockback,
+                        can_hit: hit.can_hit(ctx.hurtbox.team),
+                        owner: Some(ctx.entity),
+                        effects: hit.effects.clone(),
+                        hits: 0,
+                    },
+                });
+                ctx.finish_ability()
+            }
+            Action::Blast { windup, active, recovery, distance, hit } => {
+                ctx.hurtbox.invincible = true;
+                let direction = ctx.direction();
+                if frame < 8 {
+                    ctx.velocity.0 = direction * -10.0;
+                }
+
+                if frame < *windup {
+                    ctx.telegraph(ctx.position + direction * *distance, hit.size(), 0.0, frame, *windup);
+                }
+                if frame == *windup {
+                    ctx.brain.attack = ctx.attack_ids.next();
+                }
+                if frame >= *windup && frame < windup + active {
+                    ctx.hitbox(ctx.position + direction * *distance, hit);
+                }
+                if frame < windup + active + recovery {
+                    return Status::Running;
+                }
+                ctx.finish_ability()
+            }
+            // shake side to side, then spawn the minions in a line perpendicular to the player
+            Action::SpawnMinions { at_frame, duration, shake, minions } => {
+                ctx.hurtbox.invincible = true;
+                let direction = ctx.direction();
+                let perpendicular = Vec2::new(-direction.y, direction.x);
+                ctx.velocity.0 = if frame % 2 == 0 {
+                    perpendicular * *shake
+                } else {
+                    perpendicular * -*shake
+                };
+
+                if frame == *at_frame {
+                    for (minion, offset) in minions.iter() {
+                        ctx.minions.push((*minion, ctx.position + perpendicular * *offset));
+                    }
+                }
+                if frame < *duration {
+                    return Status::Running;
+                }
+                ctx.finish_ability()
+            }
+        }
+    }
+}
+
+// `resume` is the rest of the path to the action that was left running last frame
+fn tick(node: &Node, resume: Option<&[usize]>, ctx: &mut Context) -> Status {
+    match node {
+        Node::Sequence(children) => {
+            let resume = resume.filter(|path| !path.is_empty());
+            let start = resume.map_or(0, |path| path[0]);
+            for i in start..children.len() {
+                let status = tick_child(children, i, start, resume, ctx);
+                if status != Status::Success {
+                    return status;
+                }
+            }
+            Status::Success
+        }
+        Node::Selector(children) => {
+            let resume = resume.filter(|path| !path.is_empty());
+            let start = resume.map_or(0, |path| path[0]);
+            for i in start..children.len() {
+                let status = tick_child(children, i, start, resume, ctx);
+                if status != Status::Failure {
+                    return status;
+                }
+            }
+            Status::Failure
+        }
+        Node::Random(children) => {
+            let resume = resume.filter(|path| !path.is_empty());
+            let i = match resume {
+                Some(path) => path[0],
+                None => ctx.rng.rng.gen_range(0..children.len()),
+            };
+            tick_child(children, i, i, resume, ctx)
+        }
+        Node::Condition(condition) => {
+            if condition.check(ctx) {
+                Status::Success
+            } else {
+                Status::Failure
+            }
+        }
+        Node::Action(action) => {
+            if resume.is_none() {
+                ctx.brain.frame = 0;
+            }
+            action.run(ctx)
+        }
+    }
+}
+
+fn tick_child(children: &[Node], i: usize, start: usize, resume: Option<&[usize]>, ctx: &mut Context) -> Status {
+    let resume = if i == start {
+        resume.map(|path| &path[1..])
+    } else {
+        None
+    };
+    ctx.path.push(i);
+    let status = tick(&children[i], resume, ctx);
+    // leave the path pointing at a running action so it can be resumed next frame
+    if status != Status::Running {
+        ctx.path.pop();
+    }
+    status
+}
+
+pub fn brain_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    trees: Res<BehaviorTrees>,
+    factions: Res<Factions>,
+    interface: Res<Interface>,
+    mut rng: ResMut<GameRng>,
+    grid: Option<Res<NavGrid>>,
+    flow_field: Res<FlowField>,
+    mut attack_ids: ResMut<AttackIds>,
+    mut dialogue_events: EventReader<DialogueEvent>,
+    mut hitbox: EventWriter<HitBoxEvent>,
+    mut projectiles: EventWriter<SpawnProjectileEvent>,
+    mut telegraphs: EventWriter<TelegraphEvent>,
+    player: Query<&Transform, With<Player>>,
+    mut brains: Query<(Entity, &mut Brain, &mut Hurtbox, &mut Velocity, Option<&mut Navigator>, &Transform, &Handle<ColorMaterial>, Option<&StatusEffects>), Without<Player>>,
+) {
+    // provoking happens while the dialogue still has the game paused, so remember it for later
+    for event in dialogue_events.iter() {
+        if let (DialogueAction::Provoke, Some(speaker)) = (&event.action, event.speaker) {
+            if let Ok((_, mut brain, ..)) = brains.get_mut(speaker) {
+                brain.provoked = true;
+            }
+        }
+    }
+
+    if *interface != Interface::None {
+        return;
+    }
+
+    let target = match player.single() {
+        Ok(transform) => transform.translation.truncate(),
+        Err(_) => return,
+    };
+
+    for (entity, mut brain, mut hurtbox, mut velocity, mut navigator, transform, material, status) in brains.iter_mut() {
+        let behavior = match trees.0.get(&brain.tree) {
+            Some(behavior) => behavior,
+            None => continue,
+        };
+        if is_stunned(status) {
+            continue;
+        }
+
+        if hurtbox.is_hit || brain.provoked {
+            brain.provoked = false;
+            if let Some(team) = behavior.provoked_team {
+                hurtbox.team = team;
+            }
+        }
+        if hurtbox.is_hit {
+            hurtbox.is_hit = false;
+            hurtbox.invincible = false;
+            brain.stagger = behavior.stagger;
+            brain.running.clear();
+        }
+
+        let health = hurtbox.health as f32 / hurtbox.max_health as f32;
+        while let Some(next) = behavior.phases.get(brain.phase) {
+            if health > next.health {
+                break;
+            }
+            brain.phase += 1;
+            brain.transitioning = true;
+            brain.stagger = 0;
+            brain.running.clear();
+        }
+        if brain.transitioning {
+            hurtbox.invincible = true;
+        }
+
+        brain.age += 1;
+        brain.since_ability += 1;
+        let mut tint = None;
+        if brain.stagger > 0 {
+            brain.stagger -= 1;
+        } else {
+            let node = match (brain.phase, brain.transitioning) {
+                (0, _) => &behavior.root,
+                (phase, true) => &behavior.phases[phase - 1].transition,
+                (phase, false) => &behavior.phases[phase - 1].root,
+            };
+            let resume = brain.running.clone();
+            let mut ctx = Context {
+                entity,
+                position: transform.translation.truncate(),
+                target,
+                brain: &mut *brain,
+                hurtbox: &mut *hurtbox,
+                velocity: &mut *velocity,
+                navigator: navigator.as_deref_mut(),
+                flow: grid.as_deref().map(|grid| (grid, &*flow_field)),
+                factions: &factions,
+                rng: &mut *rng,
+                attack_ids: &mut *attack_ids,
+                path: vec![],
+                tint: None,
+                hitboxes: vec![],
+                projectiles: vec![],
+                telegraphs: vec![],
+                minions: vec![],
+            };
+            let resume = if resume.is_empty() { None } else { Some(&resume[..]) };
+            let status = tick(node, resume, &mut ctx);
+
+            let path = std::mem::take(&mut ctx.path);
+            tint = ctx.tint;
+            for event in ctx.hitboxes.drain(..) {
+                hitbox.send(event);
+            }
+            for event in ctx.projectiles.drain(..) {
+                projectiles.send(event);
+            }
+            for event in ctx.telegraphs.drain(..) {
+                telegraphs.send(event);
+            }
+            for (minion, position) in ctx.minions.drain(..) {
+                minion.spawn(&mut commands, &mut materials, position, &ctx.brain.difficulty);
+            }
+
+            if status == Status::Running {
+                brain.running = path;
+                brain.frame += 1;
+                if brain.transitioning {
+                    hurtbox.invincible = true;
+                }
+            } else {
+                brain.running.clear();
+                if brain.transitioning {
+                    brain.transitioning = false;
+                    brain.since_ability = 0;
+                    hurtbox.invincible = false;
+                }
+            }
+        }
+
+        // flush red while winding up so the attack can be seen coming
+        if let Some(material) = materials.get_mut(material) {
+            let base = brain.color;
+            material.color = match tint {
+                Some(t) => Color::rgb(base.r() + (1.0 - base.r()) * t, base.g() * (1.0 - t), base.b() * (1.0 - t)),
+                None => base,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::Node; // not bevy's ui node
+
+    fn flag(name: &str) -> Node {
+        Node::Action(Action::SetFlag(name.to_string()))
+    }
+
+    fn has_flag(name: &str) -> Node {
+        Node::Condition(Condition::Flag(name.to_string()))
+    }
+
+    // ticks `root` once the way brain_system does, with the player 100 units away
+    fn tick_once(root: &Node, brain: &mut Brain, attack_ids: &mut AttackIds) -> Status {
+        let factions: Factions = ron::de::from_str("(friendly_fire: false, relations: [])").unwrap();
+        let mut hurtbox = Hurtbox {
+            team: Team::Enemy,
+            size: Vec2::new(10.0, 10.0),
+            health: 10,
+            max_health: 10,
+            is_hit: false,
+            invincible: false,
+            last_hit_by: None,
+            hit_by: vec![],
+        };
+        let mut velocity = Velocity(Vec2::new(0.0, 0.0));
+        let mut rng = GameRng::new(0);
+        let resume = brain.running.clone();
+        let mut ctx = Context {
+            entity: Entity::new(0),
+            position: Vec2::new(0.0, 0.0),
+            target: Vec2::new(100.0, 0.0),
+            brain: &mut *brain,
+            hurtbox: &mut hurtbox,
+            velocity: &mut velocity,
+            navigator: None,
+            flow: None,
+            factions: &factions,
+            rng: &mut rng,
+            attack_ids,
+            path: vec![],
+            tint: None,
+            hitboxes: vec![],
+            projectiles: vec![],
+            telegraphs: vec![],
+            minions: vec![],
+        };
+        let resume = if resume.is_empty() { None } else { Some(&resume[..]) };
+        let status = tick(root, resume, &mut ctx);
+        let path = std::mem::take(&mut ctx.path);
+        if status == Status::Running {
+            brain.running = path;
+            brain.frame += 1;
+        } else {
+            brain.running.clear();
+        }
+        status
+    }
+
+    #[test]
+    fn sequence_stops_at_the_first_failure() {
+        let root = Node::Sequence(vec![flag("a"), has_flag("missing"), flag("b")]);
+        let mut brain = Brain::new("test", Color::WHITE, &Difficulty::default());
+        assert!(tick_once(&root, &mut brain, &mut AttackIds::default()) == Status::Failure);
+        assert_eq!(brain.flags, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn selector_stops_at_the_first_success() {
+        let root = Node::Selector(vec![has_flag("missing"), flag("a"), flag("b")]);
+        let mut brain = Brain::new("test", Color::WHITE, &Difficulty::default());
+        assert!(tick_once(&root, &mut brain, &mut AttackIds::default()) == Status::Success);
+        assert_eq!(brain.flags, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn failed_conditions_fail() {
+        let mut brain = Brain::new("test", Color::WHITE, &Difficulty::default());
+        let mut attack_ids = AttackIds::default();
+        let close = Node::Condition(Condition::PlayerCloserThan(50.0));
+        assert!(tick_once(&close, &mut brain, &mut attack_ids) == Status::Failure);
+        let not_far = Node::Condition(Condition::Not(Box::new(Condition::PlayerFurtherThan(50.0))));
+        assert!(tick_once(&not_far, &mut brain, &mut attack_ids) == Status::Failure);
+        let far = Node::Condition(Condition::PlayerFurtherThan(50.0));
+        assert!(tick_once(&far, &mut brain, &mut attack_ids) == Status::Success);
+    }
+
+    #[test]
+    fn running_actions_resume_where_they_left_off() {
+        let root = Node::Sequence(vec![flag("before"), Node::Action(Action::Wait(2)), flag("after")]);
+        let mut brain = Brain::new("test", Color::WHITE, &Difficulty::default());
+        let mut attack_ids = AttackIds::default();
+
+        assert!(tick_once(&root, &mut brain, &mut attack_ids) == Status::Running);
+        assert_eq!(brain.running, vec![1]);
+
+        // children before the running one aren't ticked again
+        brain.flags.clear();
+        assert!(tick_once(&root, &mut brain, &mut attack_ids) == Status::Running);
+        assert!(tick_once(&root, &mut brain, &mut attack_ids) == Status::Success);
+        assert_eq!(brain.flags, vec!["after".to_string()]);
+        assert!(brain.running.is_empty());
+    }
+
+    #[test]
+    fn each_brain_gets_its_own_contact_attack() {
+        let root = Node::Action(Action::ContactDamage(HitSpec {
+            size: (10.0, 10.0),
+            damage: 1,
+            damage_type: DamageType::default(),
+            knockback: 0.0,
+            targets: Targets::Hostile,
+            effects: vec![],
+        }));
+        let mut attack_ids = AttackIds::default();
+        let mut first = Brain::new("test", Color::WHITE, &Difficulty::default());
+        let mut second = Brain::new("test", Color::WHITE, &Difficulty::default());
+        first.age = 1;
+        second.age = 1;
+        tick_once(&root, &mut first, &mut attack_ids);
+        tick_once(&root, &mut second, &mut attack_ids);
+        assert!(first.attack != AttackId::default());
+        assert!(first.attack != second.attack);
+    }
+}
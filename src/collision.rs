@@ -4,36 +4,142 @@ use bevy::prelude::*;
 use bevy::math::f32::Vec2;
 use bevy::sprite::collide_aabb;
 use bevy_prototype_debug_lines::*;
+use serde::Deserialize;
 
+use crate::{Interface, ATTACK_MEMORY_FRAMES};
+use crate::loot::{GameRng, Reward, RewardTables};
+use crate::pickup::scatter_drops;
 use crate::player::Player;
+use crate::physics::{Mass, Velocity};
+use crate::status::{StatusEffects, StatusPayload};
 
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
 pub enum Team {
     Player,
     Enemy,
+    Townsfolk,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum Relation {
+    Hostile,
+    Neutral,
+    Allied,
+}
+
+// loaded from assets/data/factions.ron
+#[derive(Deserialize)]
+pub struct Factions {
+    pub friendly_fire: bool, // whether attacks also hit the attacker's own team and allies
+    relations: Vec<(Team, Team, Relation)>, // symmetric, a team is always allied with itself and anything unlisted is neutral
+}
+
+impl Factions {
+    pub fn relation(&self, a: Team, b: Team) -> Relation {
+        if a == b {
+            return Relation::Allied;
+        }
+        self.relations.iter()
+            .find(|(x, y, _)| (*x == a && *y == b) || (*x == b && *y == a))
+            .map_or(Relation::Neutral, |(_, _, relation)| *relation)
+    }
+
+    pub fn is_hostile(&self, a: Team, b: Team) -> bool {
+        self.relation(a, b) == Relation::Hostile
+    }
 }
 
 pub struct Hurtbox {
     pub team: Team,
     pub size: Vec2,
     pub health: u64,
+    pub max_health: u64,
     pub is_hit: bool,
     pub invincible: bool,
-    pub vel: Vec2, // TODO: maybe split it into a Physics component? not sure if its worth it.
+    pub last_hit_by: Option<Entity>,
+    pub hit_by: Vec<(AttackId, u32)>, // recent attacks that already hit, and for how many more frames they are remembered
+}
+
+impl Hurtbox {
+    pub fn was_hit_by(&self, attack: AttackId) -> bool {
+        self.hit_by.iter().any(|(id, _)| *id == attack)
+    }
+}
+
+// one instance of an attack, it hits each hurtbox at most once
+// attacks that should hit the same target several times ask for a new id for every hit
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct AttackId(u64);
+
+#[derive(Default)]
+pub struct AttackIds(u64);
+
+impl AttackIds {
+    pub fn next(&mut self) -> AttackId {
+        self.0 += 1;
+        AttackId(self.0)
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum DamageType {
+    Physical,
+    Fire,
+    Holy,
+}
+
+impl Default for DamageType {
+    fn default() -> Self {
+        DamageType::Physical
+    }
+}
+
+// damage multipliers per damage type, below 1 resists and above 1 is a weakness
+#[derive(Default)]
+pub struct Resistances(pub Vec<(DamageType, f32)>);
+
+impl Resistances {
+    pub fn new(multipliers: &[(DamageType, f32)]) -> Self {
+        Resistances(multipliers.to_vec())
+    }
+
+    pub fn multiplier(&self, damage_type: DamageType) -> f32 {
+        self.0.iter()
+            .find(|(kind, _)| *kind == damage_type)
+            .map_or(1.0, |(_, multiplier)| *multiplier)
+    }
+
+    pub fn resolve(resistances: Option<&Resistances>, damage: u64, damage_type: DamageType) -> u64 {
+        let multiplier = resistances.map_or(1.0, |resistances| resistances.multiplier(damage_type));
+        (damage as f32 * multiplier).round() as u64
+    }
+}
+
+// solid level geometry, projectiles break on it
+pub struct Wall {
+    pub size: Vec2,
 }
 
+// who an attack can hit, relative to the team of whoever made it
+#[derive(Clone)]
 pub enum CanHitTeam {
-    Player,
-    Enemy,
-    //All,
+    Hostile(Team), // only teams hostile to this one
+    NotAllied(Team), // hostile and neutral teams, so neutrals can be provoked
+    All,
 }
 impl CanHitTeam {
-    fn can_hit(&self, team: &Team) -> bool {
-        match (self, team) {
-            (CanHitTeam::Enemy, Team::Enemy) => true,
-            (CanHitTeam::Player, Team::Player) => true,
-            //(CanHitTeam::All, Team::Player) => true,
-            //(CanHitTeam::All, Team::Enemy) => true,
-            _ => false,
+    pub fn can_hit(&self, team: Team, factions: &Factions) -> bool {
+        match *self {
+            CanHitTeam::All => true,
+            CanHitTeam::Hostile(attacker) => match factions.relation(attacker, team) {
+                Relation::Hostile => true,
+                Relation::Neutral => false,
+                Relation::Allied => factions.friendly_fire,
+            },
+            CanHitTeam::NotAllied(attacker) => match factions.relation(attacker, team) {
+                Relation::Hostile | Relation::Neutral => true,
+                Relation::Allied => factions.friendly_fire,
+            },
         }
     }
 }
@@ -42,17 +148,37 @@ pub struct HitBoxEvent {
     pub position: Vec2,
     pub size: Vec2,
     pub damage: u64,
+    pub damage_type: DamageType,
     pub knockback: f32,
     pub can_hit: CanHitTeam,
+    pub attack: AttackId,
+    pub owner: Option<Entity>,
+    pub target: Option<Entity>, // when set only this hurtbox can be hit, for attacks that already know what they hit
+    pub effects: Vec<StatusPayload>,
 }
 
 pub fn take_damage(
-    mut entities: Query<(&mut Hurtbox, &mut Transform)>,
+    mut entities: Query<(Entity, &mut Hurtbox, &Transform, Option<&mut Velocity>, Option<&Mass>, Option<&mut StatusEffects>, Option<&Resistances>)>,
     mut hitbox_events: EventReader<HitBoxEvent>,
+    factions: Res<Factions>,
+    interface: Res<Interface>,
 ) {
+    if *interface == Interface::None {
+        for (_, mut hurtbox, ..) in entities.iter_mut() {
+            for (_, frames) in hurtbox.hit_by.iter_mut() {
+                *frames = frames.saturating_sub(1);
+            }
+            hurtbox.hit_by.retain(|(_, frames)| *frames > 0);
+        }
+    }
+
     for hitbox in hitbox_events.iter() {
-        for (mut hurtbox, transform) in entities.iter_mut() {
-            if hitbox.can_hit.can_hit(&hurtbox.team) && !hurtbox.invincible &&
+        for (entity, mut hurtbox, transform, velocity, mass, status, resistances) in entities.iter_mut() {
+            if hitbox.target.map_or(false, |target| target != entity) {
+                continue;
+            }
+            if hitbox.owner != Some(entity) && hitbox.can_hit.can_hit(hurtbox.team, &factions) &&
+                !hurtbox.invincible && !hurtbox.was_hit_by(hitbox.attack) &&
                 collide_aabb::collide(
                     transform.translation,
                     hurtbox.size,
@@ -62,34 +188,43 @@ pub fn take_damage(
                 .is_some()
             {
                 hurtbox.is_hit = true;
-                hurtbox.invincible = true;
-                hurtbox.health = hurtbox.health.saturating_sub(hitbox.damage);
-                let direction = transform.translation.truncate() - hitbox.position;
-                hurtbox.vel = direction.normalize() * hitbox.knockback;
+                hurtbox.hit_by.push((hitbox.attack, ATTACK_MEMORY_FRAMES));
+                hurtbox.last_hit_by = hitbox.owner;
+                let damage = Resistances::resolve(resistances, hitbox.damage, hitbox.damage_type);
+                hurtbox.health = hurtbox.health.saturating_sub(damage);
+                if let Some(mut velocity) = velocity {
+                    let direction = transform.translation.truncate() - hitbox.position;
+                    let mass = mass.map_or(1.0, |mass| mass.0);
+                    velocity.0 = direction.normalize() * hitbox.knockback / mass;
+                }
+                if let Some(mut status) = status {
+                    for effect in hitbox.effects.iter() {
+                        status.apply(effect);
+                    }
+                }
             }
         }
     }
 }
 
-pub fn physics_system(mut entities: Query<(&mut Hurtbox, &mut Transform)>) {
-    for (mut hurtbox, mut transform) in entities.iter_mut() {
-        //apply vel and friction
-        transform.translation += hurtbox.vel.extend(0.0);
-        hurtbox.vel *= 0.8;
-    }
-}
-
 pub fn die_system(
     mut commands: Commands,
-    entities: Query<(Entity, &Hurtbox)>,
-    mut player: Query<&mut Player>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    reward_tables: Res<RewardTables>,
+    mut rng: ResMut<GameRng>,
+    entities: Query<(Entity, &Hurtbox, &Transform, Option<&Reward>)>,
+    mut players: Query<&mut Player>,
 ) {
-    for (entity, Hurtbox { health, .. }) in entities.iter() {
-        if *health <= 0 {
+    for (entity, hurtbox, transform, reward) in entities.iter() {
+        if hurtbox.health == 0 {
             commands.entity(entity).despawn_recursive();
-            if let Ok(mut player) = player.single_mut() {
-                player.exp += 100;
-                player.money += 200;
+            if let Some(table) = reward.and_then(|reward| reward_tables.0.get(&reward.table)) {
+                // only the player's killing blows are rewarded, not deaths to other enemies or the level
+                if let Some(Ok(mut player)) = hurtbox.last_hit_by.map(|killer| players.get_mut(killer)) {
+                    player.exp += table.exp;
+                    let drops = table.drops(&mut rng);
+                    scatter_drops(&mut commands, &mut materials, &mut rng, drops, transform.translation.truncate());
+                }
             }
         }
     }
@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// data files live next to the other assets and are read once at startup
+pub fn load<T: DeserializeOwned>(path: &str) -> T {
+    let path = Path::new("assets").join(path);
+    let source = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err));
+    ron::de::from_str(&source)
+        .unwrap_or_else(|err| panic!("failed to parse {}: {}", path.display(), err))
+}
+
+// loads every .ron file in an asset directory, keyed by file name without the extension
+pub fn load_dir<T: DeserializeOwned>(dir: &str) -> HashMap<String, T> {
+    let mut result = HashMap::new();
+    let entries = fs::read_dir(Path::new("assets").join(dir))
+        .unwrap_or_else(|err| panic!("failed to read assets/{}: {}", dir, err));
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(false, |ext| ext == "ron") {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            let file = format!("{}/{}.ron", dir, name);
+            result.insert(name, load(&file));
+        }
+    }
+    result
+}
+
+// saves are written next to the game rather than into assets
+pub fn save<T: Serialize>(path: &str, value: &T) {
+    let path = Path::new(path);
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let source = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .unwrap_or_else(|err| panic!("failed to write {}: {}", path.display(), err));
+    if let Err(err) = fs::write(path, source) {
+        eprintln!("failed to save {}: {}", path.display(), err);
+    }
+}
+
+// None if there is no save yet or it can't be read
+pub fn load_save<T: DeserializeOwned>(path: &str) -> Option<T> {
+    let source = fs::read_to_string(path).ok()?;
+    ron::de::from_str(&source).ok()
+}
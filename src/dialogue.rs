@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::menu::MENU_KEYS;
+use crate::player::Player;
+use crate::{Interface, TextBox, DIALOGUE_FONT_SIZE, DIALOGUE_REVEAL_SPEED, TEXTBOX_FONT_SIZE};
+
+#[derive(Deserialize)]
+pub struct DialogueScript {
+    pub start: String,
+    pub lines: HashMap<String, DialogueLine>,
+}
+
+#[derive(Deserialize)]
+pub struct DialogueLine {
+    pub speaker: String,
+    pub text: String,
+    #[serde(default)]
+    pub next: Option<String>, // ends the conversation when None and there are no choices
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+}
+
+#[derive(Deserialize)]
+pub struct DialogueChoice {
+    pub text: String,
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub action: Option<DialogueAction>,
+}
+
+#[derive(Deserialize, Clone)]
+pub enum DialogueAction {
+    OpenShop(String), // which stock in shop.ron
+    Provoke,
+}
+
+// sent when a choice with an action is picked, `speaker` is the entity that started the conversation
+pub struct DialogueEvent {
+    pub speaker: Option<Entity>,
+    pub action: DialogueAction,
+}
+
+// every script in assets/dialogue, keyed by file name
+pub struct DialogueScripts(pub HashMap<String, DialogueScript>);
+
+// starts a conversation the first time the player comes within `radius`
+pub struct DialogueTrigger {
+    pub script: String,
+    pub radius: f32,
+    pub triggered: bool,
+}
+
+impl DialogueTrigger {
+    pub fn new(script: &str, radius: f32) -> Self {
+        DialogueTrigger {
+            script: script.to_string(),
+            radius,
+            triggered: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Dialogue {
+    speaker: Option<Entity>,
+    script: String,
+    line: Option<String>,
+    revealed: usize,
+}
+
+impl Dialogue {
+    pub fn is_active(&self) -> bool {
+        self.line.is_some()
+    }
+
+    pub fn start(&mut self, scripts: &DialogueScripts, script: &str, speaker: Option<Entity>) {
+        self.speaker = speaker;
+        self.script = script.to_string();
+        self.goto(scripts.0.get(script).map(|script| script.start.clone()));
+    }
+
+    fn goto(&mut self, line: Option<String>) {
+        self.line = line;
+        self.revealed = 0;
+    }
+
+    fn current<'a>(&self, scripts: &'a DialogueScripts) -> Option<&'a DialogueLine> {
+        let line = self.line.as_ref()?;
+        scripts.0.get(&self.script)?.lines.get(line)
+    }
+}
+
+pub fn dialogue_trigger_system(
+    scripts: Res<DialogueScripts>,
+    mut dialogue: ResMut<Dialogue>,
+    mut interface: ResMut<Interface>,
+    player: Query<&Transform, With<Player>>,
+    mut triggers: Query<(Entity, &mut DialogueTrigger, &Transform)>,
+) {
+    if *interface != Interface::None {
+        return;
+    }
+
+    if let Ok(player_transform) = player.single() {
+        for (entity, mut trigger, transform) in triggers.iter_mut() {
+            let distance = (player_transform.translation - transform.translation).truncate().length();
+            if !trigger.triggered && distance < trigger.radius {
+                trigger.triggered = true;
+                dialogue.start(&scripts, &trigger.script, Some(entity));
+                if dialogue.is_active() {
+                    *interface = Interface::Dialogue;
+                    return;
+                }
+            }
+        }
+    }
+}
+
+pub fn dialogue_system(
+    keyboard_input: Res<Input<KeyCode>>,
+    scripts: Res<DialogueScripts>,
+    mut dialogue: ResMut<Dialogue>,
+    mut interface: ResMut<Interface>,
+    mut events: EventWriter<DialogueEvent>,
+    mut text: Query<&mut Text, With<TextBox>>,
+) {
+    if *interface != Interface::Dialogue {
+        return;
+    }
+
+    let advance = keyboard_input.just_pressed(KeyCode::E) || keyboard_input.just_pressed(KeyCode::Return);
+    if let Some(line) = dialogue.current(&scripts) {
+        let length = line.text.chars().count();
+        if dialogue.revealed < length {
+            dialogue.revealed = if advance {
+                length
+            } else {
+                dialogue.revealed + DIALOGUE_REVEAL_SPEED
+            };
+        }
+        else if line.choices.is_empty() {
+            if advance {
+                dialogue.goto(line.next.clone());
+            }
+        }
+        else {
+            for (choice, key) in line.choices.iter().zip(MENU_KEYS.iter()) {
+                if keyboard_input.just_pressed(*key) {
+                    if let Some(action) = choice.action.clone() {
+                        events.send(DialogueEvent {
+                            speaker: dialogue.speaker,
+                            action,
+                        });
+                    }
+                    dialogue.goto(choice.next.clone());
+                }
+            }
+        }
+    }
+
+    if let Ok(mut text) = text.single_mut() {
+        if let Some(line) = dialogue.current(&scripts) {
+            let mut value = format!("{}: {}", line.speaker, line.text.chars().take(dialogue.revealed).collect::<String>());
+            if dialogue.revealed >= line.text.chars().count() {
+                for (i, choice) in line.choices.iter().take(MENU_KEYS.len()).enumerate() {
+                    value += &format!("\n{}. {}", i + 1, choice.text);
+                }
+            }
+            text.sections[0].value = value;
+            text.sections[0].style.font_size = DIALOGUE_FONT_SIZE;
+        }
+        else {
+            text.sections[0].value = String::new();
+            text.sections[0].style.font_size = TEXTBOX_FONT_SIZE;
+        }
+    }
+
+    if !dialogue.is_active() {
+        *interface = Interface::None;
+    }
+}
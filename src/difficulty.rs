@@ -0,0 +1,130 @@
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::data;
+use crate::loot::GameRng;
+use crate::menu::{set_menu_text, spawn_menu, MenuText, MENU_KEYS};
+use crate::{Interface, SAVE_FILE};
+
+// multipliers for enemy stats, applied when enemies spawn and to their ai timers
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Difficulty {
+    pub name: String,
+    pub enemy_health: f32,
+    pub enemy_damage: f32,
+    pub enemy_speed: f32,
+    pub ability_cooldown: f32, // above 1 enemies wait longer between abilities
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty {
+            name: "Normal".to_string(),
+            enemy_health: 1.0,
+            enemy_damage: 1.0,
+            enemy_speed: 1.0,
+            ability_cooldown: 1.0,
+        }
+    }
+}
+
+impl Difficulty {
+    pub fn health(&self, health: u64) -> u64 {
+        ((health as f32 * self.enemy_health).round() as u64).max(1)
+    }
+
+    pub fn damage(&self, damage: u64) -> u64 {
+        if damage == 0 {
+            return 0;
+        }
+        ((damage as f32 * self.enemy_damage).round() as u64).max(1)
+    }
+
+    pub fn speed(&self, speed: f32) -> f32 {
+        speed * self.enemy_speed
+    }
+
+    pub fn frames(&self, frames: u64) -> u64 {
+        (frames as f32 * self.ability_cooldown).round() as u64
+    }
+}
+
+// loaded from assets/data/difficulty.ron, the last one is meant to be edited by hand
+pub struct DifficultyPresets(pub Vec<Difficulty>);
+
+// what a run needs to be picked up again
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RunSave {
+    pub seed: u64,
+    pub difficulty: Difficulty,
+}
+
+pub struct DifficultyMenu;
+
+// shown when a new game starts, the game stays paused until a difficulty is picked
+pub fn difficulty_menu_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    presets: Res<DifficultyPresets>,
+    mut difficulty: ResMut<Difficulty>,
+    mut rng: ResMut<GameRng>,
+    mut interface: ResMut<Interface>,
+    mut saved: Local<Option<RunSave>>,
+    menus: Query<Entity, With<DifficultyMenu>>,
+    mut text: Query<&mut Text, With<MenuText>>,
+) {
+    if *interface != Interface::Difficulty {
+        return;
+    }
+    if menus.iter().next().is_none() {
+        *saved = data::load_save(SAVE_FILE);
+        spawn_menu(&mut commands, &asset_server, &mut materials, DifficultyMenu);
+        return;
+    }
+
+    // the last key is kept for continuing
+    let shown = presets.0.len().min(MENU_KEYS.len() - 1);
+    let mut value = String::from("New Game - Choose a difficulty\n\n");
+    for (i, preset) in presets.0.iter().take(shown).enumerate() {
+        value += &format!(
+            "{}. {} - health x{}, damage x{}, speed x{}, cooldowns x{}\n",
+            i + 1,
+            preset.name,
+            preset.enemy_health,
+            preset.enemy_damage,
+            preset.enemy_speed,
+            preset.ability_cooldown,
+        );
+    }
+    if let Some(save) = &*saved {
+        value += &format!("\n{}. Continue the last run ({})\n", shown + 1, save.difficulty.name);
+    }
+    set_menu_text(&mut text, value);
+
+    let mut chosen = None;
+    for (i, key) in MENU_KEYS.iter().enumerate() {
+        if keyboard_input.just_pressed(*key) {
+            match i.cmp(&shown) {
+                // a new game gets a new seed, only continuing reuses the saved one
+                Ordering::Less => chosen = Some(RunSave { seed: rand::random(), difficulty: presets.0[i].clone() }),
+                Ordering::Equal => chosen = (*saved).clone(),
+                Ordering::Greater => {}
+            }
+        }
+    }
+
+    if let Some(run) = chosen {
+        *rng = GameRng::new(run.seed);
+        *difficulty = run.difficulty.clone();
+        data::save(SAVE_FILE, &run);
+
+        *interface = Interface::None;
+        for entity in menus.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
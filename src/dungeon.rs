@@ -0,0 +1,285 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{tile_position, SOLID_TILES};
+
+const FLOOR: i32 = -1;
+const WALL: i32 = 1;
+const DECORATIONS: [i32; 2] = [58, 59];
+const MIN_ROOMS: usize = 3;
+const MAX_ATTEMPTS: u32 = 200; // layouts tried before giving up on the settings
+
+// how a level in assets/data/levels.ron is generated
+#[derive(Deserialize)]
+pub struct DungeonSettings {
+    pub seed: Option<u64>, // None uses the seed of the run
+    pub width: i32,
+    pub height: i32,
+    pub room_attempts: u32,
+    pub room_size: (i32, i32), // smallest and largest side of a room, in tiles
+    pub corridor_width: i32,
+    pub decoration_chance: f64,
+}
+
+impl DungeonSettings {
+    // catches the settings `attempt` would panic on
+    fn validate(&self) -> Result<(), String> {
+        let (min_size, max_size) = self.room_size;
+        if min_size < 1 || min_size > max_size {
+            return Err(format!("room_size {:?} needs 1 <= smallest <= largest", self.room_size));
+        }
+        // rooms have to fit inside the wall around the edge
+        if max_size > self.width - 2 || max_size > self.height - 2 {
+            return Err(format!("room_size {:?} doesn't fit in {}x{}", self.room_size, self.width, self.height));
+        }
+        if self.corridor_width < 1 {
+            return Err(format!("corridor_width {} has to be at least 1", self.corridor_width));
+        }
+        if !(0.0..=1.0).contains(&self.decoration_chance) {
+            return Err(format!("decoration_chance {} has to be between 0 and 1", self.decoration_chance));
+        }
+        Ok(())
+    }
+}
+
+// a hand-made map, the tiles are laid out the way `spawn_tiles` reads them: bottom row first
+#[derive(Deserialize)]
+pub struct DrawnMap {
+    pub width: i32,
+    pub start: (i32, i32),
+    pub tiles: Vec<i32>,
+}
+
+#[derive(Deserialize)]
+pub enum Layout {
+    Generated(DungeonSettings),
+    Drawn(DrawnMap),
+}
+
+#[derive(Clone, Copy)]
+pub struct Room {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Room {
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    // rooms keep at least one tile of wall between them
+    fn overlaps(&self, other: &Room) -> bool {
+        self.x - 1 < other.x + other.width
+            && other.x - 1 < self.x + self.width
+            && self.y - 1 < other.y + other.height
+            && other.y - 1 < self.y + self.height
+    }
+
+    // a tile away from the walls, so big bodies don't spawn inside them
+    pub fn random_tile(&self, rng: &mut impl Rng) -> (i32, i32) {
+        (
+            rng.gen_range(self.x + 1..(self.x + self.width - 1).max(self.x + 2)),
+            rng.gen_range(self.y + 1..(self.y + self.height - 1).max(self.y + 2)),
+        )
+    }
+}
+
+// the tiles and rooms of a level, generated or drawn
+#[derive(Clone)]
+pub struct Dungeon {
+    pub width: i32,
+    pub height: i32,
+    pub tiles: Vec<i32>,
+    pub rooms: Vec<Room>,
+    pub start: usize, // the room the player starts in
+    pub boss: usize, // the room furthest from the start, where the shopkeeper is
+}
+
+impl Dungeon {
+    // `name` is the level's, for the panic when its settings can't work
+    pub fn new(layout: &Layout, seed: u64, name: &str) -> Self {
+        match layout {
+            Layout::Generated(settings) => Self::generate(settings, settings.seed.unwrap_or(seed), name),
+            Layout::Drawn(map) => Self::drawn(map),
+        }
+    }
+
+    // the start is the only room, so it's also where the boss room things go
+    fn drawn(map: &DrawnMap) -> Self {
+        let start = Room {
+            x: map.start.0,
+            y: map.start.1,
+            width: 1,
+            height: 1,
+        };
+        Dungeon {
+            width: map.width,
+            height: map.tiles.len() as i32 / map.width,
+            tiles: map.tiles.clone(),
+            rooms: vec![start],
+            start: 0,
+            boss: 0,
+        }
+    }
+
+    fn generate(settings: &DungeonSettings, seed: u64, name: &str) -> Self {
+        if let Err(err) = settings.validate() {
+            panic!("bad dungeon settings for level {}: {}", name, err);
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        for _ in 0..MAX_ATTEMPTS {
+            let dungeon = Self::attempt(settings, &mut rng);
+            if dungeon.rooms.len() >= MIN_ROOMS && dungeon.all_reachable() {
+                return dungeon;
+            }
+        }
+        panic!("level {} didn't fit {} connected rooms in {} tries, give it more space or room_attempts", name, MIN_ROOMS, MAX_ATTEMPTS);
+    }
+
+    fn attempt(settings: &DungeonSettings, rng: &mut StdRng) -> Self {
+        let mut dungeon = Dungeon {
+            width: settings.width,
+            height: settings.height,
+            tiles: vec![WALL; (settings.width * settings.height) as usize],
+            rooms: vec![],
+            start: 0,
+            boss: 0,
+        };
+
+        let (min_size, max_size) = settings.room_size;
+        for _ in 0..settings.room_attempts {
+            let width = rng.gen_range(min_size..=max_size);
+            let height = rng.gen_range(min_size..=max_size);
+            // the edge of the map is always wall
+            let room = Room {
+                x: rng.gen_range(1..settings.width - width),
+                y: rng.gen_range(1..settings.height - height),
+                width,
+                height,
+            };
+            if dungeon.rooms.iter().any(|other| room.overlaps(other)) {
+                continue;
+            }
+
+            dungeon.carve(room.x, room.y, room.width, room.height);
+            // every room is joined to the one before it, so they all form one chain
+            if let Some(previous) = dungeon.rooms.last() {
+                let (from, to) = (previous.center(), room.center());
+                let corner = if rng.gen_bool(0.5) { (to.0, from.1) } else { (from.0, to.1) };
+                dungeon.carve_corridor(from, corner, settings.corridor_width);
+                dungeon.carve_corridor(corner, to, settings.corridor_width);
+            }
+            dungeon.rooms.push(room);
+        }
+
+        let distances = dungeon.distances_from(dungeon.rooms.first().map_or((0, 0), |room| room.center()));
+        dungeon.boss = (0..dungeon.rooms.len())
+            .max_by_key(|i| distances[dungeon.index(dungeon.rooms[*i].center())].unwrap_or(0))
+            .unwrap_or(0);
+
+        for tile in dungeon.tiles.iter_mut() {
+            if *tile == FLOOR && rng.gen_bool(settings.decoration_chance) {
+                *tile = DECORATIONS[rng.gen_range(0..DECORATIONS.len())];
+            }
+        }
+        dungeon
+    }
+
+    fn carve(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        for ty in y.max(1)..(y + height).min(self.height - 1) {
+            for tx in x.max(1)..(x + width).min(self.width - 1) {
+                let index = self.index((tx, ty));
+                self.tiles[index] = FLOOR;
+            }
+        }
+    }
+
+    // a straight corridor, `from` and `to` share a row or a column
+    fn carve_corridor(&mut self, from: (i32, i32), to: (i32, i32), width: i32) {
+        let (x, y) = (from.0.min(to.0), from.1.min(to.1));
+        let (dx, dy) = ((from.0 - to.0).abs(), (from.1 - to.1).abs());
+        self.carve(x, y, dx + width, dy + width);
+    }
+
+    pub fn index(&self, (x, y): (i32, i32)) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    fn walkable(&self, (x, y): (i32, i32)) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height && !SOLID_TILES.contains(&self.tiles[self.index((x, y))])
+    }
+
+    // steps from `start` to every tile it can reach
+    fn distances_from(&self, start: (i32, i32)) -> Vec<Option<u32>> {
+        let mut distances = vec![None; self.tiles.len()];
+        let mut open = VecDeque::new();
+        if self.walkable(start) {
+            distances[self.index(start)] = Some(0);
+            open.push_back(start);
+        }
+        while let Some((x, y)) = open.pop_front() {
+            let distance = distances[self.index((x, y))].unwrap();
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)].iter() {
+                if self.walkable(*next) && distances[self.index(*next)].is_none() {
+                    distances[self.index(*next)] = Some(distance + 1);
+                    open.push_back(*next);
+                }
+            }
+        }
+        distances
+    }
+
+    fn all_reachable(&self) -> bool {
+        let distances = self.distances_from(self.rooms[self.start].center());
+        self.rooms.iter().all(|room| distances[self.index(room.center())].is_some())
+    }
+
+    pub fn room_position(&self, room: usize) -> Vec2 {
+        tile_position(self.rooms[room].center())
+    }
+
+    // every room but the start and the boss room, where the waves come from
+    pub fn spawn_rooms(&self) -> Vec<usize> {
+        (0..self.rooms.len()).filter(|i| *i != self.start && *i != self.boss).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> DungeonSettings {
+        DungeonSettings {
+            seed: None,
+            width: 30,
+            height: 24,
+            room_attempts: 30,
+            room_size: (6, 9),
+            corridor_width: 2,
+            decoration_chance: 0.05,
+        }
+    }
+
+    #[test]
+    fn generated_rooms_are_connected() {
+        let settings = settings();
+        for seed in 0..100 {
+            let dungeon = Dungeon::generate(&settings, seed, "test");
+            assert!(dungeon.rooms.len() >= MIN_ROOMS, "seed {} made {} rooms", seed, dungeon.rooms.len());
+            assert!(dungeon.all_reachable(), "seed {} has unreachable rooms", seed);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "bad dungeon settings for level test")]
+    fn rooms_too_big_for_the_map() {
+        let settings = DungeonSettings { room_size: (6, 29), ..settings() };
+        Dungeon::generate(&settings, 0, "test");
+    }
+}
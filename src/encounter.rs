@@ -0,0 +1,125 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::difficulty::Difficulty;
+use crate::dungeon::Dungeon;
+use crate::loot::GameRng;
+use crate::shopkeeper::ShopkeeperBundle;
+use crate::skeleton::SkeletonBundle;
+use crate::{tile_position, Interface};
+
+#[derive(Deserialize, Clone, Copy)]
+pub enum Enemy {
+    Skeleton,
+    SkeletonArcher,
+    Shopkeeper,
+}
+
+// everything spawned by the waves, whatever team it is on, has to die before the next cleared trigger
+pub struct WaveEnemy;
+
+impl Enemy {
+    pub fn spawn(&self, commands: &mut Commands, materials: &mut Assets<ColorMaterial>, position: Vec2, difficulty: &Difficulty) {
+        match self {
+            Enemy::Skeleton => commands.spawn_bundle(SkeletonBundle::new(materials, position, difficulty)),
+            Enemy::SkeletonArcher => commands.spawn_bundle(SkeletonBundle::archer(materials, position, difficulty)),
+            Enemy::Shopkeeper => commands.spawn_bundle(ShopkeeperBundle::new(materials, position, difficulty)),
+        }.insert(WaveEnemy);
+    }
+}
+
+// what has to happen before a wave's delay starts counting down
+#[derive(Deserialize, Clone)]
+pub enum Trigger {
+    PreviousSpawned,
+    PreviousCleared,
+}
+
+// which room of the dungeon a group comes out of
+#[derive(Deserialize, Clone)]
+pub enum SpawnPoint {
+    Room(usize), // counts through the rooms that aren't the start or the boss room, wrapping around
+    AnyRoom,
+    BossRoom,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Group {
+    pub enemy: Enemy,
+    pub count: u32,
+    pub at: SpawnPoint,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Wave {
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub delay: u32, // frames after the trigger
+    pub groups: Vec<Group>,
+}
+
+// the waves of the current level and how far through them the player is
+pub struct Encounter {
+    pub waves: Vec<Wave>,
+    pub next: usize,
+    timer: u32,
+}
+
+impl Encounter {
+    pub fn new(waves: Vec<Wave>) -> Self {
+        Encounter { waves, next: 0, timer: 0 }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next >= self.waves.len()
+    }
+}
+
+pub fn enemies_alive(wave_enemies: &Query<Entity, With<WaveEnemy>>) -> usize {
+    wave_enemies.iter().count()
+}
+
+pub fn encounter_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut encounter: ResMut<Encounter>,
+    mut rng: ResMut<GameRng>,
+    dungeon: Res<Dungeon>,
+    difficulty: Res<Difficulty>,
+    interface: Res<Interface>,
+    wave_enemies: Query<Entity, With<WaveEnemy>>,
+) {
+    if *interface != Interface::None || encounter.finished() {
+        return;
+    }
+
+    let triggered = match encounter.waves[encounter.next].trigger {
+        Trigger::PreviousSpawned => true,
+        Trigger::PreviousCleared => enemies_alive(&wave_enemies) == 0,
+    };
+    if !triggered {
+        encounter.timer = 0;
+        return;
+    }
+
+    encounter.timer += 1;
+    if encounter.timer < encounter.waves[encounter.next].delay {
+        return;
+    }
+
+    let rooms = dungeon.spawn_rooms();
+    for group in encounter.waves[encounter.next].groups.iter() {
+        let room = match group.at {
+            SpawnPoint::Room(i) if !rooms.is_empty() => rooms[i % rooms.len()],
+            SpawnPoint::AnyRoom if !rooms.is_empty() => rooms[rng.rng.gen_range(0..rooms.len())],
+            _ => dungeon.boss,
+        };
+        for _ in 0..group.count {
+            let tile = dungeon.rooms[room].random_tile(&mut rng.rng);
+            group.enemy.spawn(&mut commands, &mut materials, tile_position(tile), &difficulty);
+        }
+    }
+    encounter.next += 1;
+    encounter.timer = 0;
+}
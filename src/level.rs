@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb;
+use serde::Deserialize;
+
+use crate::collision::Hurtbox;
+use crate::difficulty::Difficulty;
+use crate::dungeon::{Dungeon, Layout};
+use crate::encounter::{enemies_alive, Encounter, Wave, WaveEnemy};
+use crate::loot::GameRng;
+use crate::npc::{spawn_npc, Npc, NpcSpec};
+use crate::pathfinding::FlowField;
+use crate::physics::{Body, Velocity};
+use crate::pickup::Pickup;
+use crate::player::Player;
+use crate::projectile::Projectile;
+use crate::shopkeeper::{Shopkeeper, ShopkeeperBundle};
+use crate::{spawn_tiles, tile_position, Interface, Tile, TileAtlas, TILE_SCALE, TILE_WIDTH};
+
+// where in the level a door is
+#[derive(Deserialize, Clone, Copy)]
+pub enum ExitRoom {
+    Start,
+    Boss,
+    Tile(i32, i32), // for drawn maps
+}
+
+#[derive(Deserialize)]
+pub struct Exit {
+    pub to: String,
+    pub room: ExitRoom,
+}
+
+#[derive(Deserialize)]
+pub struct Level {
+    pub name: String,
+    pub layout: Layout,
+    #[serde(default)]
+    pub waves: Option<String>, // file name in assets/waves, levels without waves are safe
+    #[serde(default)]
+    pub shopkeeper: bool, // waits in the boss room
+    #[serde(default)]
+    pub npcs: Vec<NpcSpec>,
+    pub exits: Vec<Exit>,
+}
+
+// walking into one of these unloads the level and loads the one it leads to
+pub struct Door {
+    pub to: usize,
+}
+
+// the levels in assets/data/levels.ron, which one the player is in, which were cleared and where the shopkeeper was beaten
+pub struct Levels {
+    pub list: Vec<Level>,
+    waves: HashMap<String, Vec<Wave>>,
+    pub current: usize,
+    pub cleared: Vec<bool>,
+    pub boss_defeated: Vec<bool>,
+    on_door: bool, // doors only work when walked into, not when arrived on
+}
+
+impl Levels {
+    pub fn new(list: Vec<Level>, waves: HashMap<String, Vec<Wave>>) -> Self {
+        let cleared = list.iter().map(|level| level.waves.is_none()).collect();
+        let boss_defeated = vec![false; list.len()];
+        Levels {
+            list,
+            waves,
+            current: 0,
+            cleared,
+            boss_defeated,
+            on_door: false,
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.cleared = self.list.iter().map(|level| level.waves.is_none()).collect();
+        self.boss_defeated = vec![false; self.list.len()];
+        self.on_door = false;
+    }
+
+    fn index_of(&self, name: &str) -> usize {
+        self.list.iter()
+            .position(|level| level.name == name)
+            .unwrap_or_else(|| panic!("no level called {}", name))
+    }
+
+    fn door_tile(dungeon: &Dungeon, room: ExitRoom) -> (i32, i32) {
+        let room = match room {
+            ExitRoom::Start => dungeon.rooms[dungeon.start],
+            ExitRoom::Boss => dungeon.rooms[dungeon.boss],
+            ExitRoom::Tile(x, y) => return (x, y),
+        };
+        (room.x + 1, room.y + room.height - 2)
+    }
+
+    // the player comes out of the door leading back, or the start room if there isn't one
+    fn arrival(&self, dungeon: &Dungeon, from: usize) -> Vec2 {
+        self.list[self.current].exits.iter()
+            .find(|exit| exit.to == self.list[from].name)
+            .map_or(dungeon.room_position(dungeon.start), |exit| tile_position(Self::door_tile(dungeon, exit.room)))
+    }
+}
+
+// spawns the tiles, doors and people of a level and sets up its waves, the caller places the player
+pub fn load_level(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    levels: &mut Levels,
+    encounter: &mut Encounter,
+    atlas: &TileAtlas,
+    difficulty: &Difficulty,
+    index: usize,
+    seed: u64,
+) -> Dungeon {
+    levels.current = index;
+    let level = &levels.list[index];
+    let dungeon = Dungeon::new(&level.layout, seed.wrapping_add(index as u64), &level.name);
+    spawn_tiles(dungeon.tiles.clone(), dungeon.width as u32, atlas.0.clone(), commands);
+
+    let door_material = materials.add(Color::rgb(0.45, 0.25, 0.1).into());
+    for exit in level.exits.iter() {
+        commands.spawn_bundle(SpriteBundle {
+            material: door_material.clone(),
+            transform: Transform::from_translation(tile_position(Levels::door_tile(&dungeon, exit.room)).extend(0.1)),
+            sprite: Sprite::new(Vec2::splat(TILE_SCALE * TILE_WIDTH)),
+            ..Default::default()
+        }).insert(Door { to: levels.index_of(&exit.to) });
+    }
+
+    // he stays dead once beaten
+    if level.shopkeeper && !levels.boss_defeated[index] {
+        commands.spawn_bundle(ShopkeeperBundle::new(materials, dungeon.room_position(dungeon.boss), difficulty));
+    }
+    for npc in level.npcs.iter() {
+        spawn_npc(commands, materials, npc);
+    }
+
+    // cleared levels stay empty
+    *encounter = Encounter::new(level.waves.as_ref().map_or(vec![], |waves| levels.waves[waves].clone()));
+    if levels.cleared[index] {
+        encounter.next = encounter.waves.len();
+    }
+
+    commands.insert_resource(dungeon.clone());
+    dungeon
+}
+
+pub fn door_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut levels: ResMut<Levels>,
+    mut encounter: ResMut<Encounter>,
+    mut flow_field: ResMut<FlowField>,
+    atlas: Res<TileAtlas>,
+    difficulty: Res<Difficulty>,
+    rng: Res<GameRng>,
+    interface: Res<Interface>,
+    mut player: Query<(&mut Transform, &mut Velocity, &Body), With<Player>>,
+    doors: Query<(&Door, &Transform), Without<Player>>,
+    wave_enemies: Query<Entity, With<WaveEnemy>>,
+    shopkeepers: Query<&Hurtbox, With<Shopkeeper>>,
+    level_entities: Query<Entity, Or<(With<Tile>, With<Door>, With<Npc>, With<Pickup>, With<Projectile>)>>,
+    enemies: Query<Entity, (With<Hurtbox>, Without<Player>)>,
+) {
+    if shopkeepers.iter().any(|hurtbox| hurtbox.health == 0) {
+        let current = levels.current;
+        levels.boss_defeated[current] = true;
+    }
+
+    if *interface != Interface::None {
+        return;
+    }
+
+    if encounter.finished() && enemies_alive(&wave_enemies) == 0 {
+        let current = levels.current;
+        levels.cleared[current] = true;
+    }
+
+    let (mut transform, mut velocity, body) = match player.single_mut() {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+    let touching = doors.iter()
+        .find(|(_, door_transform)| {
+            collide_aabb::collide(transform.translation, body.size, door_transform.translation, Vec2::splat(TILE_SCALE * TILE_WIDTH)).is_some()
+        })
+        .map(|(door, _)| door.to);
+    let was_on_door = std::mem::replace(&mut levels.on_door, touching.is_some());
+    let to = match touching {
+        Some(to) if !was_on_door => to,
+        _ => return,
+    };
+
+    for entity in level_entities.iter().chain(enemies.iter()) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let from = levels.current;
+    let dungeon = load_level(&mut commands, &mut materials, &mut levels, &mut encounter, &atlas, &difficulty, to, rng.seed);
+    *flow_field = FlowField::default();
+    transform.translation = levels.arrival(&dungeon, from).extend(transform.translation.z);
+    velocity.0 = Vec2::new(0.0, 0.0);
+}
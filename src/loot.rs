@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::Deserialize;
+
+use crate::collision::Hurtbox;
+use crate::pickup::coins;
+use crate::player::Player;
+use crate::shop::ShopItems;
+use crate::weapon::Inventory;
+
+// every random roll in the game goes through this so a run can be replayed from its seed
+pub struct GameRng {
+    pub seed: u64,
+    pub rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub enum LootDrop {
+    Nothing,
+    Money(u64),
+    Heal(u64),
+    Item(String), // name of a shop item, whose effect is applied when picked up
+}
+
+impl LootDrop {
+    pub fn apply(&self, shop_items: &ShopItems, player: &mut Player, hurtbox: &mut Hurtbox, inventory: &mut Inventory) {
+        match self {
+            LootDrop::Nothing => {}
+            LootDrop::Money(amount) => player.money += amount,
+            LootDrop::Heal(amount) => hurtbox.health = (hurtbox.health + amount).min(hurtbox.max_health),
+            LootDrop::Item(name) => {
+                if let Some(item) = shop_items.find(name) {
+                    item.effect.apply(player, hurtbox, inventory);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LootEntry {
+    pub weight: u32,
+    pub drop: LootDrop,
+}
+
+#[derive(Deserialize)]
+pub struct RewardTable {
+    pub exp: u64,
+    pub money: u64,
+    #[serde(default)]
+    pub loot: Vec<LootEntry>,
+}
+
+impl RewardTable {
+    pub fn roll(&self, rng: &mut GameRng) -> LootDrop {
+        self.loot.choose_weighted(&mut rng.rng, |entry| entry.weight)
+            .map(|entry| entry.drop.clone())
+            .unwrap_or(LootDrop::Nothing)
+    }
+
+    // the money as coins plus one roll on the loot table
+    pub fn drops(&self, rng: &mut GameRng) -> Vec<LootDrop> {
+        let mut drops = coins(self.money);
+        drops.push(self.roll(rng));
+        drops
+    }
+}
+
+// loaded from assets/data/rewards.ron, keyed by enemy type
+pub struct RewardTables(pub HashMap<String, RewardTable>);
+
+// what the entity is worth to whoever lands the killing blow
+pub struct Reward {
+    pub table: String,
+}
+
+impl Reward {
+    pub fn new(table: &str) -> Self {
+        Reward {
+            table: table.to_string(),
+        }
+    }
+}
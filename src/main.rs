@@ -1,17 +1,54 @@
+// bevy systems take their resources and queries as arguments, so they get long
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+mod behavior;
 mod collision;
+mod data;
+mod dialogue;
+mod difficulty;
+mod dungeon;
+mod encounter;
+mod level;
+mod loot;
+mod menu;
 mod mouse;
+mod npc;
+mod pathfinding;
+mod physics;
+mod pickup;
 mod player;
+mod progression;
+mod projectile;
+mod shop;
 mod shopkeeper;
 mod skeleton;
+mod status;
+mod telegraph;
+mod weapon;
 
 use bevy::window::WindowMode;
+use behavior::{brain_system, BehaviorTrees};
 use collision::{
-    debug_hitboxes, debug_hurtboxes, die_system, physics_system, take_damage, HitBoxEvent, Hurtbox,
+    debug_hitboxes, debug_hurtboxes, die_system, take_damage, AttackIds, Factions, HitBoxEvent, Hurtbox, Wall,
 };
+use dialogue::{dialogue_system, dialogue_trigger_system, Dialogue, DialogueEvent, DialogueScripts};
+use difficulty::{difficulty_menu_system, Difficulty, DifficultyPresets};
+use dungeon::Dungeon;
+use encounter::{encounter_system, Encounter};
+use level::{door_system, load_level, Door, Levels};
+use loot::{GameRng, RewardTables};
 use mouse::{mouse_system, MouseState};
+use npc::{wander_system, Npc};
+use pathfinding::{flow_field_system, navigation_system, FlowField, NavGrid};
+use physics::{physics_system, separation_system, wall_collision_system, BodySettings, GroundFriction};
+use pickup::{pickup_system, Pickup};
 use player::{player_system, Player, PlayerBundle};
-use shopkeeper::{shopkeeper_system, ShopkeeperBundle};
-use skeleton::skeleton_system;
+use progression::{level_up_system, LevelUpEvent, Progression};
+use projectile::{projectile_system, spawn_projectiles, Projectile, SpawnProjectileEvent};
+use shop::{shop_system, Shop, ShopItems};
+use status::{status_icon_system, status_system};
+use telegraph::{telegraph_system, TelegraphEvent};
+use weapon::{equipment_system, Weapons};
 
 use bevy::core::FixedTimestep;
 use bevy::prelude::*;
@@ -22,6 +59,8 @@ const WINDOW_HEIGHT: f32 = 720.;
 
 const TILE_SCALE: f32 = 8.0;
 const TILE_WIDTH: f32 = 8.0;
+const SOLID_TILES: [i32; 1] = [1];
+const TILE_FRICTION: [(i32, f32); 1] = [(16, 0.95)]; // slippery tiles
 
 pub const PLAYER_SPRITE_ROWS: u32 = 2; // TODO: Surely these are redundant and can be derived from the image width / 50...?
 pub const PLAYER_SPRITE_COLS: u32 = 2;
@@ -31,8 +70,36 @@ pub const DASH_DURATION: u32 = 6; // dash frame count
 pub const DASH_COOLDOWN_TIME: u32 = 60; //frames u need to wait betw dashes
 pub const DAMAGE_RECOIL_SPEED: f32 = 15.; // knockback force when u get damaged
 pub const DAMAGED_INVINCIBILITY_FRAMES: u32 = 5; // frames that u are invincible after being hit
+pub const INPUT_BUFFER_FRAMES: u32 = 8; // a slash or dash pressed this many frames early still happens
+pub const SHOOT_DURATION: u32 = 12; // frames u cant act after shooting
+pub const BOLT_SPEED: f32 = 14.;
+pub const BOLT_DAMAGE: u64 = 1;
+pub const BOLT_LIFETIME: u32 = 60;
+pub const DEFAULT_FRICTION: f32 = 0.8; // fraction of velocity kept each frame
+pub const ATTACK_MEMORY_FRAMES: u32 = 180; // how long a hurtbox remembers an attack that hit it
+pub const CONTACT_REHIT_FRAMES: u64 = 30; // enemies touching u hurt again this often
+pub const REPLAN_FRAMES: u32 = 30; // enemies look for a new path this often
+pub const WAYPOINT_RADIUS: f32 = 16.; // how close to a waypoint counts as reaching it
+pub const NPC_WALK_SPEED: f32 = 2.;
+pub const NPC_WALK_FRAMES: u32 = 180; // townsfolk give up on a spot they can't reach after this long
+pub const NPC_IDLE_FRAMES: u32 = 240; // longest they stand around between walks
+pub const TEXTBOX_FONT_SIZE: f32 = 100.;
+pub const DIALOGUE_FONT_SIZE: f32 = 40.;
+pub const DIALOGUE_REVEAL_SPEED: usize = 1; // characters revealed per frame
+pub const SHOP_RANGE: f32 = 120.; // how close u need to be to a shopkeeper to trade
+pub const COIN_VALUE: u64 = 10; // money dropped is split into coins of about this much
+pub const MAX_COINS_PER_DROP: u64 = 8;
+pub const PICKUP_LIFETIME: u32 = 600; // frames a pickup lies around before disappearing
+pub const PICKUP_BLINK_TIME: u32 = 120; // pickups blink for this many frames before disappearing
+pub const PICKUP_MAGNET_RADIUS: f32 = 150.; // pickups closer than this fly towards u
+pub const PICKUP_MAGNET_SPEED: f32 = 8.;
+pub const SAVE_FILE: &str = "saves/run.ron"; // the seed and difficulty of the last run
+pub const STARTING_WEAPON: &str = "sword"; // id in weapons.ron
 
 fn main() {
+    let shop_items = ShopItems(data::load("data/shop.ron"));
+    let weapons = Weapons::load("data/weapons.ron", &shop_items);
+
     App::build()
         .insert_resource(WindowDescriptor {
             title: "Townskeep".to_string(),
@@ -46,30 +113,94 @@ fn main() {
         .add_plugins(DefaultPlugins)
         .add_plugin(DebugLinesPlugin)
         .add_event::<HitBoxEvent>()
+        .add_event::<DialogueEvent>()
+        .add_event::<LevelUpEvent>()
+        .add_event::<SpawnProjectileEvent>()
+        .add_event::<TelegraphEvent>()
         .add_startup_system(setup.system())
         .add_system_set(
             SystemSet::new()
                 .with_run_criteria(FixedTimestep::steps_per_second(60.0))
                 .with_system(mouse_system.system().label("input"))
+                .with_system(difficulty_menu_system.system().before("dialogue_trigger"))
+                .with_system(dialogue_trigger_system.system().label("dialogue_trigger").before("actions"))
+                .with_system(dialogue_system.system().label("dialogue").after("dialogue_trigger"))
+                .with_system(shop_system.system().after("dialogue"))
+                .with_system(equipment_system.system().after("dialogue"))
+                .with_system(flow_field_system.system().before("actions"))
+                .with_system(encounter_system.system().before("actions"))
                 .with_system(player_system.system().label("actions").after("input"))
-                .with_system(skeleton_system.system().label("actions"))
-                .with_system(shopkeeper_system.system().label("actions"))
+                .with_system(brain_system.system().label("actions"))
+                .with_system(wander_system.system().label("actions"))
                 .with_system(die_system.system().label("actions"))
+                .with_system(pickup_system.system().label("actions"))
+                .with_system(status_system.system().label("actions"))
+                .with_system(level_up_system.system().label("level_up").after("actions"))
+                .with_system(announce_level_up.system().after("level_up"))
+                .with_system(navigation_system.system().after("actions"))
                 .with_system(physics_system.system().label("physics").after("actions"))
-                .with_system(take_damage.system().after("physics"))
+                .with_system(separation_system.system().label("separation").after("physics"))
+                .with_system(wall_collision_system.system().label("walls").after("separation"))
+                .with_system(projectile_system.system().label("projectiles").after("walls"))
+                .with_system(spawn_projectiles.system().after("actions"))
+                .with_system(take_damage.system().after("projectiles"))
                 .with_system(debug_hurtboxes.system().after("physics"))
                 .with_system(debug_hitboxes.system().after("physics"))
+                .with_system(status_icon_system.system().after("physics"))
+                .with_system(telegraph_system.system().after("actions"))
                 .with_system(update_hud.system().after("physics"))
+                .with_system(door_system.system().label("doors").after("walls"))
+                .with_system(camera_system.system().after("doors"))
                 .with_system(game_over.system())
                 .with_system(victory.system()),
         )
         .insert_resource(ClearColor(Color::rgb(0.3, 0.3, 0.3)))
         .insert_resource(MouseState::default())
+        .insert_resource(Interface::Difficulty)
+        .insert_resource(Dialogue::default())
+        .insert_resource(DialogueScripts(data::load_dir("dialogue")))
+        .insert_resource(BehaviorTrees(data::load_dir("behavior")))
+        .insert_resource(Encounter::new(vec![]))
+        .insert_resource(Difficulty::default())
+        .insert_resource(DifficultyPresets(data::load("data/difficulty.ron")))
+        .insert_resource(Levels::new(data::load("data/levels.ron"), data::load_dir("waves")))
+        .insert_resource(Shop::default())
+        .insert_resource(shop_items)
+        .insert_resource(weapons)
+        .insert_resource(data::load::<Progression>("data/progression.ron"))
+        .insert_resource(RewardTables(data::load("data/rewards.ron")))
+        .insert_resource(data::load::<Factions>("data/factions.ron"))
+        .insert_resource(AttackIds::default())
+        .insert_resource(FlowField::default())
+        .insert_resource(data::load::<BodySettings>("data/bodies.ron"))
+        .insert_resource(GameRng::new(rand::random()))
         .run();
 }
 
 pub struct MainCamera;
 
+// every tile sprite of the current level, so it can be unloaded
+pub struct Tile;
+
+pub struct TileAtlas(pub Handle<TextureAtlas>);
+
+// world position of the centre of a tile
+pub fn tile_position((x, y): (i32, i32)) -> Vec2 {
+    let tile_size = TILE_SCALE * TILE_WIDTH;
+    let start_pos = Vec2::new(-WINDOW_WIDTH / 2.0 + tile_size / 2., -WINDOW_HEIGHT / 2.0 + tile_size / 2.0);
+    start_pos + Vec2::new(x as f32, y as f32) * tile_size
+}
+
+// what currently has the player's attention, gameplay is paused unless this is `None`
+#[derive(PartialEq)]
+pub enum Interface {
+    None,
+    Dialogue,
+    Shop,
+    Equipment,
+    Difficulty, // picked at the start of every new game
+}
+
 fn spawn_tiles(
     sprite_indices: Vec<i32>,
     column_number: u32,
@@ -78,7 +209,24 @@ fn spawn_tiles(
 ) {
     let mut row = 0;
     let mut column = 0;
-    let start_pos = Vec2::new(-WINDOW_WIDTH / 2.0 + (TILE_SCALE * TILE_WIDTH) / 2., -WINDOW_HEIGHT / 2.0 + (TILE_SCALE * TILE_WIDTH) / 2.0);
+    let start_pos = tile_position((0, 0));
+
+    commands.insert_resource(GroundFriction {
+        width: column_number as i32,
+        origin: start_pos,
+        tile_size: TILE_SCALE * TILE_WIDTH,
+        friction: sprite_indices.iter()
+            .map(|ix| TILE_FRICTION.iter().find(|(tile, _)| tile == ix).map(|(_, friction)| *friction))
+            .collect(),
+    });
+
+    commands.insert_resource(NavGrid {
+        width: column_number as i32,
+        height: (sprite_indices.len() as u32 / column_number) as i32,
+        origin: start_pos,
+        tile_size: TILE_SCALE * TILE_WIDTH,
+        solid: sprite_indices.iter().map(|ix| SOLID_TILES.contains(ix)).collect(),
+    });
 
     let mut spsh_bundle = SpriteSheetBundle {
         sprite: TextureAtlasSprite::new(0),
@@ -96,7 +244,11 @@ fn spawn_tiles(
                 start_pos.y + TILE_SCALE * TILE_WIDTH * column as f32,
                 0.0,
             );
-            commands.spawn_bundle(spsh_bundle.clone());
+            let mut tile = commands.spawn_bundle(spsh_bundle.clone());
+            tile.insert(Tile);
+            if SOLID_TILES.contains(&ix) {
+                tile.insert(Wall { size: Vec2::splat(TILE_SCALE * TILE_WIDTH) });
+            }
         }
 
         row += 1;
@@ -110,36 +262,22 @@ fn spawn_tiles(
 
 fn setup(
     mut commands: Commands,
-    materials: ResMut<Assets<ColorMaterial>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut levels: ResMut<Levels>,
+    mut encounter: ResMut<Encounter>,
+    difficulty: Res<Difficulty>,
+    rng: Res<GameRng>,
 ) {
     //Tilemap
 
     let tilemap_texture = asset_server.load("graphics/Tilemap/colored_tilemap_packed.png");
     let tilemap_atlas =
         TextureAtlas::from_grid(tilemap_texture, Vec2::new(TILE_WIDTH, TILE_WIDTH), 14, 10);
-    let tilemap_atlas_handle = texture_atlases.add(tilemap_atlas);
-
-    spawn_tiles(
-        vec![
-        58, 59, 58, 58, 58, 59, 58, 58, 59, 58, 58, 58, 59, 58, 58, 58,
-        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
-        -1, -1, -1, -1, -1, 59, -1, -1, 58, -1, -1, 59, -1, -1, -1, -1,
-        -1, -1, 58, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
-        -1, -1, -1, 59, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1,
-        -1, -1, 58, -1, -1, -1, -1, -1, 59, -1, -1, -1, -1, -1, -1, -1,
-        -1, -1, -1, -1, 58, -1, -1, -1, -1, -1, -1, -1, 58, -1, -1, -1,
-        -1, 59, -1, -1, -1, -1, -1, 1, 1, 1, 1, -1, -1, -1, -1, -1,
-        -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, -1, 59, -1,
-        -1, -1, -1, -1, -1, -1, -1, 58, -1, -1, -1, -1, -1, -1, -1, -1,
-        58, 59, 58, 58, 58, 59, 58, 58, 59, 58, 58, 58, 59, 58, 58, 58,
-        58, -1, -1, 58, -1, 59, -1, 58, 59, -1, 58, 58, -1, -1, 58, 58,
-        ],
-        16,
-        tilemap_atlas_handle,
-        &mut commands,
-    );
+    let tilemap_atlas = TileAtlas(texture_atlases.add(tilemap_atlas));
+    let dungeon = load_level(&mut commands, &mut materials, &mut levels, &mut encounter, &tilemap_atlas, &difficulty, 0, rng.seed);
+    commands.insert_resource(tilemap_atlas);
 
     // let mut tilemap = Tilemap::builder()
     //     .auto_spawn(2, 2)
@@ -203,14 +341,14 @@ fn setup(
             ..Default::default()
         },
         ..Default::default()
-    }).insert(HUD);
+    }).insert(Hud);
 
     commands.spawn_bundle(TextBundle {
         text: Text::with_section(
             "A harmless box...",
             TextStyle {
                 font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                font_size: 100.0,
+                font_size: TEXTBOX_FONT_SIZE,
                 color: Color::rgb(0.0, 0.0, 0.0),
             },
             Default::default(),
@@ -227,84 +365,122 @@ fn setup(
         ..Default::default()
     }).insert(TextBox);
 
-    spawn_entities(commands, materials, asset_server, texture_atlases);
+    spawn_player(commands, asset_server, texture_atlases, &dungeon);
 }
 
-fn spawn_entities(
+fn spawn_player(
     mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    dungeon: &Dungeon,
 ) {
-    commands.spawn_bundle(ShopkeeperBundle::new(&mut materials, Vec2::new(400.0, 0.0)));
-
     let player_texture = asset_server.load("graphics/player.png");
     let player_atlas = TextureAtlas::from_grid(player_texture, Vec2::new(50.0, 50.0), PLAYER_SPRITE_COLS as usize, PLAYER_SPRITE_ROWS as usize);
     let player_atlas = texture_atlases.add(player_atlas);
-    commands.spawn_bundle(PlayerBundle::new(player_atlas));
+    commands.spawn_bundle(PlayerBundle::new(player_atlas, dungeon.room_position(dungeon.start)));
 }
 
-struct HUD;
-struct TextBox;
+struct Hud;
+pub struct TextBox;
 
 fn game_over(
     player: Query<&Player>,
     mut text: Query<&mut Text, With<TextBox>>,
     keyboard_input: Res<Input<KeyCode>>,
-    entities: Query<Entity, With<Hurtbox>>,
+    mut interface: ResMut<Interface>,
+    entities: Query<Entity, Or<(With<Hurtbox>, With<Pickup>, With<Projectile>, With<Tile>, With<Door>, With<Npc>)>>,
+    mut levels: ResMut<Levels>,
+    mut encounter: ResMut<Encounter>,
+    mut flow_field: ResMut<FlowField>,
+    atlas: Res<TileAtlas>,
+    difficulty: Res<Difficulty>,
+    rng: Res<GameRng>,
 
     // setup
     mut commands: Commands,
-    materials: ResMut<Assets<ColorMaterial>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
     asset_server: Res<AssetServer>,
     texture_atlases: ResMut<Assets<TextureAtlas>>
 ) {
     if player.single().is_err() {
         if let Ok(mut text) = text.single_mut() {
-            text.sections[0].value = format!("Game Over!\nPress 'n' to retry.");
+            text.sections[0].value = "Game Over!\nPress 'n' to retry.".to_string();
             if keyboard_input.just_pressed(KeyCode::N) {
                 for entity in entities.iter() {
                     commands.entity(entity).despawn_recursive();
                 }
-                spawn_entities(commands, materials, asset_server, texture_atlases);
+                // a retry starts the whole run again, from picking a difficulty
+                levels.restart();
+                *interface = Interface::Difficulty;
+                let dungeon = load_level(&mut commands, &mut materials, &mut levels, &mut encounter, &atlas, &difficulty, 0, rng.seed);
+                *flow_field = FlowField::default();
+                spawn_player(commands, asset_server, texture_atlases, &dungeon);
             }
         }
     }
-    else if keyboard_input.get_pressed().count() > 0 {
+    else if *interface == Interface::None && keyboard_input.get_pressed().count() > 0 {
         if let Ok(mut text) = text.single_mut() {
-            text.sections[0].value = format!("");
+            text.sections[0].value = String::new();
         }
     }
 }
 
+// the level is bigger than the window, so the camera follows the player
+fn camera_system(
+    player: Query<&Transform, (With<Player>, Without<MainCamera>)>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if let (Ok(player), Ok(mut camera)) = (player.single(), camera.single_mut()) {
+        camera.translation.x = player.translation.x;
+        camera.translation.y = player.translation.y;
+    }
+}
+
 fn victory(
-    player: Query<&Hurtbox, Without<Player>>,
+    levels: Res<Levels>,
     mut text: Query<&mut Text, With<TextBox>>
 ) {
-    if player.iter().count() == 0 {
+    if levels.cleared.iter().all(|cleared| *cleared) {
         if let Ok(mut text) = text.single_mut() {
-            text.sections[0].value = format!("A winner is you!");
+            text.sections[0].value = "A winner is you!".to_string();
+        }
+    }
+}
+
+fn announce_level_up(
+    mut level_ups: EventReader<LevelUpEvent>,
+    interface: Res<Interface>,
+    mut text: Query<&mut Text, With<TextBox>>
+) {
+    for level_up in level_ups.iter() {
+        if *interface == Interface::None {
+            if let Ok(mut text) = text.single_mut() {
+                text.sections[0].value = format!("Level {}!", level_up.level);
+            }
         }
     }
 }
 
 fn update_hud(
     player: Query<(&Hurtbox, &Player)>,
-    mut text: Query<&mut Text, With<HUD>>
+    progression: Res<Progression>,
+    encounter: Res<Encounter>,
+    levels: Res<Levels>,
+    mut text: Query<&mut Text, With<Hud>>
 ) {
-    if let Ok((Hurtbox { health, .. }, player)) = player.single() {
+    if let Ok((Hurtbox { health, max_health, .. }, player)) = player.single() {
         if let Ok(mut text) = text.single_mut() {
             text.sections[0].value = format!(
-                "Health: {}\nMoney: {}\nEXP: {}/{}\nLevel: {}",
+                "Health: {}/{}\nMoney: {}\nLevel: {} ({}/{} EXP)\n{} - Wave: {}/{}",
                 health,
+                max_health,
                 player.money,
+                player.level,
                 player.exp,
-                100,
-                player.level()
-            );
-            text.sections[0].value = format!(
-                "Health: {}",
-                health,
+                progression.exp_to_next_level(player.level),
+                levels.list[levels.current].name,
+                encounter.next,
+                encounter.waves.len(),
             );
         }
     }
@@ -0,0 +1,58 @@
+use bevy::ecs::component::Component;
+use bevy::prelude::*;
+
+pub const MENU_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+    KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+    KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
+pub struct MenuText;
+
+// a panel in the middle of the screen, tagged with `marker` so it can be despawned again
+pub fn spawn_menu<T: Component>(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    materials: &mut Assets<ColorMaterial>,
+    marker: T,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(15.0),
+                    left: Val::Percent(20.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Percent(60.0), Val::Percent(60.0)),
+                padding: Rect::all(Val::Px(20.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::FlexStart,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.1, 0.1, 0.1, 0.9).into()),
+            ..Default::default()
+        })
+        .insert(marker)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                    Default::default(),
+                ),
+                ..Default::default()
+            }).insert(MenuText);
+        });
+}
+
+pub fn set_menu_text(text: &mut Query<&mut Text, With<MenuText>>, value: String) {
+    if let Ok(mut text) = text.single_mut() {
+        text.sections[0].value = value;
+    }
+}
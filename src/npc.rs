@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::dialogue::DialogueTrigger;
+use crate::loot::GameRng;
+use crate::pathfinding::NavGrid;
+use crate::physics::{Body, PhysicsBundle, Velocity};
+use crate::shop::Merchant;
+use crate::{
+    tile_position, Interface, NPC_IDLE_FRAMES, NPC_WALK_FRAMES, NPC_WALK_SPEED, TILE_SCALE, TILE_WIDTH, WAYPOINT_RADIUS,
+};
+
+// a townsperson placed by a level in levels.ron
+#[derive(Deserialize)]
+pub struct NpcSpec {
+    pub tile: (i32, i32),
+    pub color: (f32, f32, f32),
+    #[serde(default)]
+    pub dialogue: Option<String>, // script in assets/dialogue, said when the player comes close
+    #[serde(default)]
+    pub shop: Option<String>, // stock in shop.ron, traded with E
+    #[serde(default)]
+    pub wander: f32, // how many tiles from where they were placed they stroll, they stand still at 0
+}
+
+// can't be hurt and never fights
+pub struct Npc;
+
+// strolls to a random spot near `home`, waits a bit, and picks another
+pub struct Wander {
+    home: Vec2,
+    radius: f32,
+    target: Option<Vec2>,
+    frames: u32, // left to idle, or to walk before giving up on the target
+}
+
+#[derive(Bundle)]
+pub struct NpcBundle {
+    npc: Npc,
+    wander: Wander,
+    body: Body,
+    #[bundle]
+    physics: PhysicsBundle,
+    #[bundle]
+    sprite: SpriteBundle
+}
+
+impl NpcBundle {
+    pub fn new(materials: &mut Assets<ColorMaterial>, spec: &NpcSpec) -> Self {
+        let position = tile_position(spec.tile);
+        let (r, g, b) = spec.color;
+        Self {
+            npc: Npc,
+            wander: Wander {
+                home: position,
+                radius: spec.wander * TILE_SCALE * TILE_WIDTH,
+                target: None,
+                frames: NPC_IDLE_FRAMES,
+            },
+            body: Body { size: Vec2::new(30.0, 50.0), push: 0.5 },
+            physics: PhysicsBundle::new(1.0),
+            sprite: SpriteBundle {
+                material: materials.add(Color::rgb(r, g, b).into()),
+                transform: Transform::from_translation(position.extend(0.0)),
+                sprite: Sprite::new(Vec2::new(30.0, 50.0)),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+pub fn spawn_npc(commands: &mut Commands, materials: &mut Assets<ColorMaterial>, spec: &NpcSpec) {
+    let mut npc = commands.spawn_bundle(NpcBundle::new(materials, spec));
+    if let Some(script) = &spec.dialogue {
+        npc.insert(DialogueTrigger::new(script, 150.0));
+    }
+    if let Some(stock) = &spec.shop {
+        npc.insert(Merchant::new(stock));
+    }
+}
+
+pub fn wander_system(
+    interface: Res<Interface>,
+    mut rng: ResMut<GameRng>,
+    grid: Option<Res<NavGrid>>,
+    mut npcs: Query<(&mut Wander, &mut Velocity, &Transform)>,
+) {
+    if *interface != Interface::None {
+        return;
+    }
+
+    for (mut wander, mut velocity, transform) in npcs.iter_mut() {
+        if wander.radius <= 0.0 {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        wander.frames = wander.frames.saturating_sub(1);
+
+        match wander.target {
+            Some(target) if wander.frames > 0 && (target - position).length() > WAYPOINT_RADIUS => {
+                velocity.0 = (target - position).normalize() * NPC_WALK_SPEED;
+            }
+            Some(_) => {
+                wander.target = None;
+                wander.frames = rng.rng.gen_range(NPC_IDLE_FRAMES / 2..=NPC_IDLE_FRAMES);
+            }
+            None if wander.frames == 0 => {
+                let offset = Vec2::new(rng.rng.gen_range(-1.0..=1.0), rng.rng.gen_range(-1.0..=1.0)) * wander.radius;
+                let target = wander.home + offset;
+                // only somewhere they could actually walk to
+                let walkable = grid.as_ref().map_or(true, |grid| {
+                    grid.tile_at(target).map_or(false, |tile| grid.walkable(tile))
+                });
+                if walkable {
+                    wander.target = Some(target);
+                    wander.frames = NPC_WALK_FRAMES;
+                }
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use bevy::prelude::*;
+
+use crate::player::Player;
+use crate::{Interface, REPLAN_FRAMES, WAYPOINT_RADIUS};
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+// which tiles of the level can be walked on, built when the tiles are spawned
+pub struct NavGrid {
+    pub width: i32,
+    pub height: i32,
+    pub origin: Vec2, // centre of tile (0, 0)
+    pub tile_size: f32,
+    pub solid: Vec<bool>,
+}
+
+impl NavGrid {
+    // the tile under `position`, even if it's off the grid
+    pub fn tile_coords(&self, position: Vec2) -> (i32, i32) {
+        let local = (position - self.origin) / self.tile_size;
+        (local.x.round() as i32, local.y.round() as i32)
+    }
+
+    pub fn tile_at(&self, position: Vec2) -> Option<(i32, i32)> {
+        let tile = self.tile_coords(position);
+        if self.in_bounds(tile) {
+            Some(tile)
+        } else {
+            None
+        }
+    }
+
+    pub fn center(&self, (x, y): (i32, i32)) -> Vec2 {
+        self.origin + Vec2::new(x as f32, y as f32) * self.tile_size
+    }
+
+    pub fn in_bounds(&self, (x, y): (i32, i32)) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height
+    }
+
+    pub fn index(&self, (x, y): (i32, i32)) -> usize {
+        (y * self.width + x) as usize
+    }
+
+    pub fn walkable(&self, tile: (i32, i32)) -> bool {
+        self.in_bounds(tile) && !self.solid[self.index(tile)]
+    }
+
+    // walkable neighbours and the cost of stepping to them, diagonals can't cut corners
+    pub fn neighbours(&self, (x, y): (i32, i32)) -> Vec<((i32, i32), u32)> {
+        let mut result = vec![];
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+                let next = (x + dx, y + dy);
+                if !self.walkable(next) {
+                    continue;
+                }
+                if dx != 0 && dy != 0 {
+                    if !self.walkable((x + dx, y)) || !self.walkable((x, y + dy)) {
+                        continue;
+                    }
+                    result.push((next, DIAGONAL_COST));
+                } else {
+                    result.push((next, STRAIGHT_COST));
+                }
+            }
+        }
+        result
+    }
+
+    // a* from the tile under `from` to the tile under `to`, as a list of tile centres
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.tile_at(from)?;
+        let goal = self.tile_at(to)?;
+        if !self.walkable(start) || !self.walkable(goal) {
+            return None;
+        }
+
+        let heuristic = |(x, y): (i32, i32)| {
+            let (dx, dy) = ((x - goal.0).abs() as u32, (y - goal.1).abs() as u32);
+            STRAIGHT_COST * dx.max(dy) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dy)
+        };
+
+        let mut cost = vec![u32::MAX; self.solid.len()];
+        let mut came_from: Vec<Option<(i32, i32)>> = vec![None; self.solid.len()];
+        let mut open = BinaryHeap::new();
+        cost[self.index(start)] = 0;
+        open.push(Reverse((heuristic(start), start)));
+
+        while let Some(Reverse((_, tile))) = open.pop() {
+            if tile == goal {
+                let mut path = vec![self.center(tile)];
+                let mut current = tile;
+                while let Some(previous) = came_from[self.index(current)] {
+                    path.push(self.center(previous));
+                    current = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let current_cost = cost[self.index(tile)];
+            for (next, step) in self.neighbours(tile) {
+                let next_cost = current_cost + step;
+                if next_cost < cost[self.index(next)] {
+                    cost[self.index(next)] = next_cost;
+                    came_from[self.index(next)] = Some(tile);
+                    open.push(Reverse((next_cost + heuristic(next), next)));
+                }
+            }
+        }
+        None
+    }
+
+    // whether a straight walk between two points stays on walkable tiles
+    pub fn line_of_sight(&self, from: Vec2, to: Vec2) -> bool {
+        let steps = ((to - from).length() / (self.tile_size / 4.0)).ceil().max(1.0) as u32;
+        (0..=steps).all(|i| {
+            let point = from + (to - from) * (i as f32 / steps as f32);
+            self.tile_at(point).map_or(false, |tile| self.walkable(tile))
+        })
+    }
+
+    // drops every waypoint that can be skipped by walking straight past it
+    pub fn smooth(&self, from: Vec2, path: Vec<Vec2>) -> Vec<Vec2> {
+        let mut result = vec![];
+        let mut current = from;
+        let mut i = 0;
+        while i < path.len() {
+            let mut furthest = i;
+            while furthest + 1 < path.len() && self.line_of_sight(current, path[furthest + 1]) {
+                furthest += 1;
+            }
+            current = path[furthest];
+            result.push(current);
+            i = furthest + 1;
+        }
+        result
+    }
+}
+
+// lets an entity walk around walls towards `target`, re-planning every REPLAN_FRAMES
+#[derive(Default)]
+pub struct Navigator {
+    pub target: Option<Vec2>,
+    path: Vec<Vec2>,
+    frames_until_replan: u32,
+}
+
+impl Navigator {
+    // direction to the next waypoint, None when there is no path
+    pub fn heading(&self, position: Vec2) -> Option<Vec2> {
+        self.path.first().map(|waypoint| (*waypoint - position).normalize())
+    }
+}
+
+pub fn navigation_system(
+    grid: Option<Res<NavGrid>>,
+    interface: Res<Interface>,
+    mut navigators: Query<(&mut Navigator, &Transform)>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+    if *interface != Interface::None {
+        return;
+    }
+
+    for (mut navigator, transform) in navigators.iter_mut() {
+        let position = transform.translation.truncate();
+        let target = match navigator.target {
+            Some(target) => target,
+            None => continue,
+        };
+
+        navigator.frames_until_replan = navigator.frames_until_replan.saturating_sub(1);
+        if navigator.frames_until_replan == 0 {
+            navigator.frames_until_replan = REPLAN_FRAMES;
+            navigator.path = match grid.find_path(position, target) {
+                Some(mut path) => {
+                    // walk to the real target rather than the centre of its tile
+                    if let Some(last) = path.last_mut() {
+                        *last = target;
+                    }
+                    grid.smooth(position, path)
+                }
+                None => vec![],
+            };
+        }
+
+        while navigator.path.first().map_or(false, |waypoint| (*waypoint - position).length() < WAYPOINT_RADIUS) {
+            navigator.path.remove(0);
+        }
+    }
+}
+
+// distance to the player from every tile, built once per step and shared by every chasing enemy
+#[derive(Default)]
+pub struct FlowField {
+    goal: Option<(i32, i32)>,
+    distance: Vec<u32>,
+}
+
+impl FlowField {
+    // dijkstra outwards from `goal` over the walkable tiles
+    pub fn build(grid: &NavGrid, goal: (i32, i32)) -> Self {
+        let mut distance = vec![u32::MAX; grid.solid.len()];
+        let mut open = BinaryHeap::new();
+        if grid.walkable(goal) {
+            distance[grid.index(goal)] = 0;
+            open.push(Reverse((0, goal)));
+        }
+
+        while let Some(Reverse((cost, tile))) = open.pop() {
+            if cost > distance[grid.index(tile)] {
+                continue;
+            }
+            for (next, step) in grid.neighbours(tile) {
+                if cost + step < distance[grid.index(next)] {
+                    distance[grid.index(next)] = cost + step;
+                    open.push(Reverse((cost + step, next)));
+                }
+            }
+        }
+
+        FlowField {
+            goal: Some(goal),
+            distance,
+        }
+    }
+
+    // direction towards the neighbouring tile closest to the goal, None on the goal tile or off the field
+    pub fn heading(&self, grid: &NavGrid, position: Vec2) -> Option<Vec2> {
+        let tile = grid.tile_at(position)?;
+        let here = *self.distance.get(grid.index(tile))?;
+        if here == 0 || here == u32::MAX {
+            return None;
+        }
+        let (next, _) = grid.neighbours(tile)
+            .into_iter()
+            .min_by_key(|(next, _)| self.distance[grid.index(*next)])?;
+        Some((grid.center(next) - position).normalize())
+    }
+}
+
+pub fn flow_field_system(
+    grid: Option<Res<NavGrid>>,
+    mut flow_field: ResMut<FlowField>,
+    player: Query<&Transform, With<Player>>,
+) {
+    if let (Some(grid), Ok(transform)) = (grid, player.single()) {
+        // the field only changes when the player moves to another tile
+        if let Some(goal) = grid.tile_at(transform.translation.truncate()) {
+            if flow_field.goal != Some(goal) {
+                *flow_field = FlowField::build(&grid, goal);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    // one string per row starting from y = 0, # is solid, tiles are one unit wide
+    fn parse_grid(rows: &[&str]) -> NavGrid {
+        NavGrid {
+            width: rows[0].len() as i32,
+            height: rows.len() as i32,
+            origin: Vec2::new(0.0, 0.0),
+            tile_size: 1.0,
+            solid: rows.iter().flat_map(|row| row.chars().map(|c| c == '#')).collect(),
+        }
+    }
+
+    fn tiles(grid: &NavGrid, path: &[Vec2]) -> Vec<(i32, i32)> {
+        path.iter().map(|point| grid.tile_at(*point).unwrap()).collect()
+    }
+
+    #[test]
+    fn path_goes_around_walls() {
+        let grid = parse_grid(&[
+            ".....",
+            ".###.",
+            ".....",
+        ]);
+        let path = grid.find_path(grid.center((2, 0)), grid.center((2, 2))).unwrap();
+        let path = tiles(&grid, &path);
+        assert_eq!(path.first(), Some(&(2, 0)));
+        assert_eq!(path.last(), Some(&(2, 2)));
+        assert!(path.iter().all(|tile| grid.walkable(*tile)));
+        assert!(path.windows(2).all(|step| (step[0].0 - step[1].0).abs() <= 1 && (step[0].1 - step[1].1).abs() <= 1));
+    }
+
+    #[test]
+    fn diagonals_dont_cut_corners() {
+        let grid = parse_grid(&[
+            "..",
+            "#.",
+        ]);
+        assert!(!grid.neighbours((0, 0)).iter().any(|(tile, _)| *tile == (1, 1)));
+        let path = grid.find_path(grid.center((0, 0)), grid.center((1, 1))).unwrap();
+        assert_eq!(tiles(&grid, &path), vec![(0, 0), (1, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn no_path_to_a_walled_off_goal() {
+        let grid = parse_grid(&[
+            "..#.",
+            "..#.",
+        ]);
+        assert!(grid.find_path(grid.center((0, 0)), grid.center((3, 0))).is_none());
+        assert!(grid.find_path(grid.center((0, 0)), grid.center((2, 0))).is_none());
+    }
+
+    #[test]
+    fn smoothing_skips_waypoints_in_sight() {
+        let grid = parse_grid(&[
+            "...",
+            "##.",
+            "...",
+        ]);
+        let start = grid.center((0, 0));
+        let path = grid.find_path(start, grid.center((0, 2))).unwrap();
+        let smoothed = grid.smooth(start, path.clone());
+        assert!(smoothed.len() < path.len());
+        assert_eq!(smoothed.last(), path.last());
+
+        // every waypoint left is needed: it can be walked to, but the one after it can't be seen
+        let mut from = start;
+        for (i, waypoint) in smoothed.iter().enumerate() {
+            assert!(grid.line_of_sight(from, *waypoint));
+            if let Some(next) = smoothed.get(i + 1) {
+                assert!(!grid.line_of_sight(from, *next));
+            }
+            from = *waypoint;
+        }
+
+        // in the open the whole path collapses into its end
+        let open = parse_grid(&["....", "....", "...."]);
+        let path = open.find_path(open.center((0, 0)), open.center((3, 2))).unwrap();
+        assert_eq!(open.smooth(open.center((0, 0)), path), vec![open.center((3, 2))]);
+    }
+
+    #[test]
+    fn flow_field_leads_to_the_goal_as_fast_as_astar() {
+        let grid = parse_grid(&[
+            "......",
+            ".####.",
+            "....#.",
+            "###.#.",
+            "......",
+        ]);
+        let goal = (0, 2);
+        let field = FlowField::build(&grid, goal);
+        for start in [(5, 0), (0, 4), (5, 4), (2, 0)].iter() {
+            let mut position = grid.center(*start);
+            let mut steps = 0;
+            while let Some(heading) = field.heading(&grid, position) {
+                position = grid.center(grid.tile_at(position + heading * grid.tile_size).unwrap());
+                steps += 1;
+                assert!(steps < 100, "no way to the goal from {:?}", start);
+            }
+            assert_eq!(grid.tile_at(position), Some(goal));
+            let path = grid.find_path(grid.center(*start), grid.center(goal)).unwrap();
+            assert_eq!(steps, path.len() - 1, "from {:?}", start);
+        }
+    }
+
+    // a big open arena with scattered wall segments
+    fn arena() -> NavGrid {
+        let (width, height) = (64, 64);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut solid = vec![false; (width * height) as usize];
+        for _ in 0..80 {
+            let (x, y) = (rng.gen_range(0..width - 6), rng.gen_range(0..height));
+            for dx in 0..6 {
+                solid[(y * width + x + dx) as usize] = true;
+            }
+        }
+        NavGrid {
+            width,
+            height,
+            origin: Vec2::new(0.0, 0.0),
+            tile_size: 64.0,
+            solid,
+        }
+    }
+
+    // timing only, nothing to assert: cargo test --release -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_flow_field_against_astar() {
+        let grid = arena();
+        let mut rng = StdRng::seed_from_u64(3);
+        let goal = grid.center((32, 32));
+        let agents: Vec<Vec2> = (0..100)
+            .map(|_| grid.center((rng.gen_range(0..grid.width), rng.gen_range(0..grid.height))))
+            .collect();
+        let steps = 60;
+
+        let start = Instant::now();
+        let mut found = 0;
+        for _ in 0..steps {
+            found = agents.iter().filter(|agent| grid.find_path(**agent, goal).is_some()).count();
+        }
+        let astar = start.elapsed();
+
+        let start = Instant::now();
+        let mut headed = 0;
+        for _ in 0..steps {
+            let field = FlowField::build(&grid, grid.tile_at(goal).unwrap());
+            headed = agents.iter().filter(|agent| field.heading(&grid, **agent).is_some()).count();
+        }
+        let flow = start.elapsed();
+
+        println!("{} agents over {} steps: a* {:?} ({} paths), flow field {:?} ({} headings)", agents.len(), steps, astar, found, flow, headed);
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::pathfinding::NavGrid;
+use crate::player::Player;
+use crate::status::StatusEffects;
+use crate::{Interface, DEFAULT_FRICTION};
+
+pub struct Velocity(pub Vec2);
+
+// fraction of the velocity kept every frame, overrides the ground
+pub struct Friction(pub f32);
+
+// knockback is divided by this
+pub struct Mass(pub f32);
+
+// the friction of every ground tile, None where the tile keeps the default
+pub struct GroundFriction {
+    pub width: i32,
+    pub origin: Vec2, // centre of tile (0, 0)
+    pub tile_size: f32,
+    pub friction: Vec<Option<f32>>,
+}
+
+impl GroundFriction {
+    pub fn at(&self, position: Vec2) -> Option<f32> {
+        let local = (position - self.origin) / self.tile_size;
+        let (x, y) = (local.x.round() as i32, local.y.round() as i32);
+        if x < 0 || y < 0 || x >= self.width {
+            return None;
+        }
+        self.friction.get((y * self.width + x) as usize).copied().flatten()
+    }
+}
+
+// solid bodies push each other apart instead of overlapping
+pub struct Body {
+    pub size: Vec2,
+    pub push: f32, // fraction of an overlap resolved each frame by moving the other body
+}
+
+// whether enemies stop the player or the player shoves them aside
+#[derive(Deserialize, PartialEq)]
+pub enum PlayerBodyMode {
+    Blocked,
+    PushThrough,
+}
+
+// loaded from assets/data/bodies.ron
+#[derive(Deserialize)]
+pub struct BodySettings {
+    pub player_mode: PlayerBodyMode,
+}
+
+#[derive(Bundle)]
+pub struct PhysicsBundle {
+    velocity: Velocity,
+    mass: Mass,
+}
+
+impl PhysicsBundle {
+    pub fn new(mass: f32) -> Self {
+        PhysicsBundle {
+            velocity: Velocity(Vec2::new(0.0, 0.0)),
+            mass: Mass(mass),
+        }
+    }
+}
+
+pub fn physics_system(
+    mut entities: Query<(&mut Velocity, &mut Transform, Option<&Friction>, Option<&StatusEffects>)>,
+    ground: Option<Res<GroundFriction>>,
+    interface: Res<Interface>,
+) {
+    if *interface != Interface::None {
+        return;
+    }
+
+    for (mut velocity, mut transform, friction, status) in entities.iter_mut() {
+        //apply vel and friction
+        let speed = status.map_or(1.0, |status| status.speed_multiplier());
+        transform.translation += (velocity.0 * speed).extend(0.0);
+
+        let friction = match friction {
+            Some(friction) => friction.0,
+            None => ground.as_ref()
+                .and_then(|ground| ground.at(transform.translation.truncate()))
+                .unwrap_or(DEFAULT_FRICTION),
+        };
+        velocity.0 *= friction;
+    }
+}
+
+pub fn separation_system(
+    mut bodies: Query<(&Body, &mut Transform, Option<&Player>)>,
+    settings: Res<BodySettings>,
+    interface: Res<Interface>,
+) {
+    if *interface != Interface::None {
+        return;
+    }
+
+    let snapshot: Vec<(Vec2, Vec2, f32, bool)> = bodies.iter_mut()
+        .map(|(body, transform, player)| (transform.translation.truncate(), body.size, body.push, player.is_some()))
+        .collect();
+    let mut offsets = vec![Vec2::new(0.0, 0.0); snapshot.len()];
+
+    for a in 0..snapshot.len() {
+        for b in a + 1..snapshot.len() {
+            let (pos_a, size_a, push_a, player_a) = snapshot[a];
+            let (pos_b, size_b, push_b, player_b) = snapshot[b];
+            let difference = pos_a - pos_b;
+            let overlap = (size_a + size_b) / 2.0 - difference.abs();
+            if overlap.x <= 0.0 || overlap.y <= 0.0 {
+                continue;
+            }
+
+            // push out along the axis that overlaps the least
+            let sign = |x: f32| if x < 0.0 { -1.0 } else { 1.0 };
+            let separation = if overlap.x < overlap.y {
+                Vec2::new(overlap.x * sign(difference.x), 0.0)
+            } else {
+                Vec2::new(0.0, overlap.y * sign(difference.y))
+            };
+
+            let (mut share_a, mut share_b) = (push_b, push_a);
+            if player_a || player_b {
+                let player_moves = settings.player_mode == PlayerBodyMode::Blocked;
+                if player_a == player_moves {
+                    share_b = 0.0;
+                } else {
+                    share_a = 0.0;
+                }
+            }
+            let total = share_a + share_b;
+            if total > 1.0 {
+                share_a /= total;
+                share_b /= total;
+            }
+
+            offsets[a] += separation * share_a;
+            offsets[b] -= separation * share_b;
+        }
+    }
+
+    for ((_, mut transform, _), offset) in bodies.iter_mut().zip(offsets) {
+        transform.translation += offset.extend(0.0);
+    }
+}
+
+// keeps bodies out of solid tiles and inside the level
+pub fn wall_collision_system(
+    grid: Option<Res<NavGrid>>,
+    mut bodies: Query<(&Body, &mut Transform)>,
+    interface: Res<Interface>,
+) {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return,
+    };
+    if *interface != Interface::None {
+        return;
+    }
+
+    let tile_size = Vec2::splat(grid.tile_size);
+    for (body, mut transform) in bodies.iter_mut() {
+        let half = body.size / 2.0;
+        let (min_x, min_y) = grid.tile_coords(transform.translation.truncate() - half);
+        let (max_x, max_y) = grid.tile_coords(transform.translation.truncate() + half);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if grid.walkable((x, y)) {
+                    continue;
+                }
+                let difference = transform.translation.truncate() - grid.center((x, y));
+                let overlap = (body.size + tile_size) / 2.0 - difference.abs();
+                if overlap.x <= 0.0 || overlap.y <= 0.0 {
+                    continue;
+                }
+                let sign = |n: f32| if n < 0.0 { -1.0 } else { 1.0 };
+                if overlap.x < overlap.y {
+                    transform.translation.x += overlap.x * sign(difference.x);
+                } else {
+                    transform.translation.y += overlap.y * sign(difference.y);
+                }
+            }
+        }
+    }
+}
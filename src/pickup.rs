@@ -0,0 +1,119 @@
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb;
+use rand::Rng;
+
+use crate::collision::Hurtbox;
+use crate::loot::{GameRng, LootDrop};
+use crate::physics::{Friction, Velocity};
+use crate::player::Player;
+use crate::shop::ShopItems;
+use crate::weapon::Inventory;
+use crate::{Interface, COIN_VALUE, MAX_COINS_PER_DROP, PICKUP_BLINK_TIME, PICKUP_LIFETIME, PICKUP_MAGNET_RADIUS, PICKUP_MAGNET_SPEED};
+
+const PICKUP_SIZE: f32 = 16.0;
+
+#[derive(Bundle)]
+pub struct PickupBundle {
+    pickup: Pickup,
+    velocity: Velocity,
+    friction: Friction,
+    #[bundle]
+    sprite: SpriteBundle
+}
+impl PickupBundle {
+    pub fn new(materials: &mut Assets<ColorMaterial>, drop: LootDrop, position: Vec2, vel: Vec2) -> Self {
+        let color = match drop {
+            LootDrop::Money(_) => Color::rgb(1.0, 0.85, 0.0),
+            LootDrop::Heal(_) => Color::rgb(1.0, 0.2, 0.3),
+            LootDrop::Item(_) | LootDrop::Nothing => Color::rgb(0.7, 0.3, 1.0),
+        };
+        Self {
+            pickup: Pickup {
+                drop,
+                lifetime: PICKUP_LIFETIME,
+            },
+            velocity: Velocity(vel),
+            friction: Friction(0.9),
+            sprite: SpriteBundle {
+                material: materials.add(color.into()),
+                transform: Transform::from_translation(position.extend(0.5)),
+                sprite: Sprite::new(Vec2::splat(PICKUP_SIZE)),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub struct Pickup {
+    pub drop: LootDrop,
+    pub lifetime: u32, // frames left before it disappears
+}
+
+// throws the drops out from `position` in random directions
+pub fn scatter_drops(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    rng: &mut GameRng,
+    drops: Vec<LootDrop>,
+    position: Vec2,
+) {
+    for drop in drops {
+        if let LootDrop::Nothing = drop {
+            continue;
+        }
+        let angle = rng.rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = rng.rng.gen_range(3.0..8.0);
+        let vel = Vec2::new(angle.cos(), angle.sin()) * speed;
+        commands.spawn_bundle(PickupBundle::new(materials, drop, position, vel));
+    }
+}
+
+// splits an amount of money into a handful of coins
+pub fn coins(money: u64) -> Vec<LootDrop> {
+    if money == 0 {
+        return vec![];
+    }
+    let count = (money / COIN_VALUE).max(1).min(MAX_COINS_PER_DROP);
+    let mut coins: Vec<LootDrop> = (0..count).map(|_| LootDrop::Money(money / count)).collect();
+    coins[0] = LootDrop::Money(money / count + money % count);
+    coins
+}
+
+pub fn pickup_system(
+    mut commands: Commands,
+    interface: Res<Interface>,
+    shop_items: Res<ShopItems>,
+    mut player_query: Query<(&mut Player, &mut Hurtbox, &mut Inventory, &Transform)>,
+    mut pickups: Query<(Entity, &mut Pickup, &mut Velocity, &Transform, &mut Visible), Without<Player>>,
+) {
+    if *interface != Interface::None {
+        return;
+    }
+
+    let mut player = player_query.single_mut().ok();
+    for (entity, mut pickup, mut velocity, transform, mut visible) in pickups.iter_mut() {
+        if let Some((player, hurtbox, inventory, player_transform)) = &mut player {
+            if collide_aabb::collide(
+                player_transform.translation,
+                hurtbox.size,
+                transform.translation,
+                Vec2::splat(PICKUP_SIZE),
+            ).is_some() {
+                pickup.drop.apply(&shop_items, player, hurtbox, inventory);
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            let difference = (player_transform.translation - transform.translation).truncate();
+            if difference.length() < PICKUP_MAGNET_RADIUS {
+                velocity.0 = difference.normalize() * PICKUP_MAGNET_SPEED;
+            }
+        }
+
+        pickup.lifetime = pickup.lifetime.saturating_sub(1);
+        if pickup.lifetime == 0 {
+            commands.entity(entity).despawn();
+        }
+        visible.is_visible = pickup.lifetime > PICKUP_BLINK_TIME || (pickup.lifetime / 8) % 2 == 0;
+    }
+}
@@ -1,30 +1,49 @@
 use bevy::{math::vec2, prelude::*};
-use crate::{DAMAGED_INVINCIBILITY_FRAMES, DASH_COOLDOWN_TIME, DASH_DURATION, DASH_SPEED, MOVEMENT_SPEED};
+use crate::{Interface, BOLT_DAMAGE, BOLT_LIFETIME, BOLT_SPEED, DAMAGED_INVINCIBILITY_FRAMES, DASH_COOLDOWN_TIME, DASH_DURATION, DASH_SPEED, INPUT_BUFFER_FRAMES, MOVEMENT_SPEED, SHOOT_DURATION, STARTING_WEAPON};
 use crate::mouse::MouseState;
-use crate::collision::{HitBoxEvent, Team, CanHitTeam, Hurtbox};
+use crate::collision::{AttackId, AttackIds, HitBoxEvent, Team, CanHitTeam, Hurtbox, DamageType};
+use crate::physics::{Body, PhysicsBundle, Velocity};
+use crate::projectile::{Projectile, SpawnProjectileEvent};
+use crate::status::StatusEffects;
+use crate::weapon::{Inventory, Weapon, Weapons};
 
 #[derive(Bundle)]
 pub struct PlayerBundle {
     player: Player,
     hurtbox: Hurtbox,
+    inventory: Inventory,
+    status: StatusEffects,
+    body: Body,
+    #[bundle]
+    physics: PhysicsBundle,
     #[bundle]
     sprite: SpriteSheetBundle
 }
 impl PlayerBundle {
-    pub fn new(texture_atlas: Handle<TextureAtlas>) -> Self {
+    pub fn new(texture_atlas: Handle<TextureAtlas>, position: Vec2) -> Self {
         Self {
             player: Player::new(),
             hurtbox: Hurtbox {
                 size: Vec2::new(30.0, 50.0),
                 health: 20,
+                max_health: 20,
                 team: Team::Player,
                 is_hit: false,
                 invincible: false,
-                vel: Vec2::new(0.0, 0.0)
+                last_hit_by: None,
+                hit_by: vec![],
             },
+            physics: PhysicsBundle::new(1.0),
+            body: Body { size: Vec2::new(30.0, 50.0), push: 0.5 },
+            inventory: Inventory::new(STARTING_WEAPON),
+            status: StatusEffects::default(),
             sprite: SpriteSheetBundle {
-                transform: Transform::from_scale(Vec3::splat(3.0)),
-                texture_atlas: texture_atlas,
+                transform: Transform {
+                    translation: position.extend(0.0),
+                    scale: Vec3::splat(3.0),
+                    ..Default::default()
+                },
+                texture_atlas,
                 ..Default::default()
             }
         }
@@ -34,11 +53,20 @@ impl PlayerBundle {
 pub enum PlayerAction {
     Idle,
     Walk,
-    Slash { angle: f32 },
+    Slash { angle: f32, combo: usize },
+    Shoot { angle: f32 },
     Dash { angle: f32 },
     Damaged
 }
 
+// an action pressed while the player was busy, performed as soon as it is possible
+#[derive(Clone, Copy, PartialEq)]
+pub enum BufferedInput {
+    Slash,
+    Shoot,
+    Dash,
+}
+
 pub enum Facing {
     Up,
     Left,
@@ -52,8 +80,14 @@ pub struct Player {
     pub action: PlayerAction,
     pub facing: Facing,
     pub hp: i32,
-    pub exp: u64,
+    pub level: u64,
+    pub exp: u64, // exp earned towards the next level
     pub money: u64,
+    pub slash_damage: u64, // added to the damage of the equipped weapon
+    pub movement_speed: f32,
+    pub dash_cooldown: u32,
+    pub input_buffer: Option<(BufferedInput, u32)>, // the input and how many more frames it stays buffered
+    pub attack: Option<AttackId>, // the current slash, so it only hits each enemy once
 }
 
 impl Player {
@@ -64,13 +98,50 @@ impl Player {
             frame_since_last_cooldown: 0,
             action: PlayerAction::Idle,
             facing: Facing::Right,
+            level: 1,
             exp: 0,
             money: 0,
+            slash_damage: 0,
+            movement_speed: MOVEMENT_SPEED,
+            dash_cooldown: DASH_COOLDOWN_TIME,
+            input_buffer: None,
+            attack: None,
+        }
+    }
+
+    pub fn buffer_input(
+        &mut self,
+        keyboard_input: &Input<KeyCode>,
+        mouse_button_input: &Input<MouseButton>,
+    ) {
+        if mouse_button_input.just_pressed(MouseButton::Left) {
+            self.input_buffer = Some((BufferedInput::Slash, INPUT_BUFFER_FRAMES));
+        }
+        else if mouse_button_input.just_pressed(MouseButton::Right) {
+            self.input_buffer = Some((BufferedInput::Shoot, INPUT_BUFFER_FRAMES));
+        }
+        else if keyboard_input.just_pressed(KeyCode::Space) {
+            self.input_buffer = Some((BufferedInput::Dash, INPUT_BUFFER_FRAMES));
+        }
+        else if let Some((input, frames)) = self.input_buffer {
+            self.input_buffer = if frames > 0 {
+                Some((input, frames - 1))
+            } else {
+                None
+            };
         }
     }
 
-    pub fn level(&self) -> u64 {
-        self.exp / 100
+    fn buffered(&self, input: BufferedInput) -> bool {
+        matches!(self.input_buffer, Some((buffered, _)) if buffered == input)
+    }
+
+    fn start_slash(&mut self, angle: f32, combo: usize, velocity: &mut Velocity) {
+        self.input_buffer = None;
+        self.set_action(PlayerAction::Slash { angle, combo });
+        self.attack = None;
+        velocity.0 = vec2(angle.cos() * 4.0, angle.sin() * 4.0);
+        self.set_facing_by_angle(angle);
     }
 
     pub fn set_action(&mut self, action: PlayerAction) {
@@ -86,12 +157,10 @@ impl Player {
             } else {
                 Facing::Down
             }
+        } else if cos.is_sign_positive() {
+            Facing::Right
         } else {
-            if cos.is_sign_positive() {
-                Facing::Right
-            } else {
-                Facing::Left
-            }
+            Facing::Left
         }
     }
 
@@ -127,22 +196,27 @@ impl Player {
 
     pub fn check_actions(
         &mut self,
-        keyboard_input: &Input<KeyCode>,
         mouse: &MouseState,
-        mouse_button_input: &Input<MouseButton>,
-        hurtbox: &mut Hurtbox,
+        velocity: &mut Velocity,
         transform: &mut Transform
     ) -> bool {
-        if mouse_button_input.just_pressed(MouseButton::Left) {
+        if self.buffered(BufferedInput::Slash) {
             if let Some(angle) = mouse.angle_from_location_to_mouse(transform.translation.truncate()) {
-                self.set_action(PlayerAction::Slash { angle });
-                hurtbox.vel = vec2(angle.cos() * 4.0, angle.sin() * 4.0);
+                self.start_slash(angle, 0, velocity);
+            }
+            false
+        }
+        else if self.buffered(BufferedInput::Shoot) {
+            if let Some(angle) = mouse.angle_from_location_to_mouse(transform.translation.truncate()) {
+                self.input_buffer = None;
+                self.set_action(PlayerAction::Shoot { angle });
                 self.set_facing_by_angle(angle);
             }
             false
         }
-        else if keyboard_input.just_pressed(KeyCode::Space) && self.frame_since_last_cooldown > DASH_COOLDOWN_TIME as u64 {
+        else if self.buffered(BufferedInput::Dash) && self.frame_since_last_cooldown > self.dash_cooldown as u64 {
             if let Some(angle) = mouse.angle_from_location_to_mouse(transform.translation.truncate()) {
+                self.input_buffer = None;
                 self.set_action(PlayerAction::Dash { angle });
                 self.set_facing_by_angle(angle);
             }
@@ -157,14 +231,11 @@ impl Player {
         &mut self,
         keyboard_input: &Input<KeyCode>,
         mouse: &MouseState,
-        mouse_button_input: &Input<MouseButton>,
-        hurtbox: &mut Hurtbox,
+        velocity: &mut Velocity,
         transform: &mut Transform
     ) {
-        if self.check_actions(&keyboard_input, &mouse, &mouse_button_input, hurtbox, transform) && self.check_enter_walk(&keyboard_input) {
-            if self.frame > 90 {
-                self.set_action(PlayerAction::Idle);
-            }
+        if self.check_actions(mouse, velocity, transform) && self.check_enter_walk(keyboard_input) && self.frame > 90 {
+            self.set_action(PlayerAction::Idle);
         }
     }
 
@@ -172,45 +243,44 @@ impl Player {
         &mut self,
         keyboard_input: &Input<KeyCode>,
         mouse: &MouseState,
-        mouse_button_input: &Input<MouseButton>,
-        hurtbox: &mut Hurtbox,
+        velocity: &mut Velocity,
         transform: &mut Transform
     ) {
-        if self.check_actions(&keyboard_input, mouse, mouse_button_input, hurtbox, transform) && self.check_leave_walk(&keyboard_input) {
+        if self.check_actions(mouse, velocity, transform) && self.check_leave_walk(keyboard_input) {
             if keyboard_input.pressed(KeyCode::W) &&
                 keyboard_input.pressed(KeyCode::A) {
-                hurtbox.vel = vec2(-1., 1.).normalize() * MOVEMENT_SPEED;
+                velocity.0 = vec2(-1., 1.).normalize() * self.movement_speed;
                 self.facing = Facing::Left;
             }
             else if keyboard_input.pressed(KeyCode::W) &&
                 keyboard_input.pressed(KeyCode::D) {
-                hurtbox.vel = vec2(1., 1.).normalize() * MOVEMENT_SPEED;
+                velocity.0 = vec2(1., 1.).normalize() * self.movement_speed;
                 self.facing = Facing::Right;
             }
             else if keyboard_input.pressed(KeyCode::S) &&
                 keyboard_input.pressed(KeyCode::A) {
-                hurtbox.vel = vec2(-1., -1.).normalize() * MOVEMENT_SPEED;
+                velocity.0 = vec2(-1., -1.).normalize() * self.movement_speed;
                 self.facing = Facing::Left;
             }
             else if keyboard_input.pressed(KeyCode::S) &&
                 keyboard_input.pressed(KeyCode::D) {
-                hurtbox.vel = vec2(1., -1.).normalize() * MOVEMENT_SPEED;
+                velocity.0 = vec2(1., -1.).normalize() * self.movement_speed;
                 self.facing = Facing::Right;
             }
             else if keyboard_input.pressed(KeyCode::W) {
-                hurtbox.vel = vec2(0., MOVEMENT_SPEED);
+                velocity.0 = vec2(0., self.movement_speed);
                 self.facing = Facing::Up;
             }
             else if keyboard_input.pressed(KeyCode::S) {
-                hurtbox.vel = vec2(0., -MOVEMENT_SPEED);
+                velocity.0 = vec2(0., -self.movement_speed);
                 self.facing = Facing::Down;
             }
             else if keyboard_input.pressed(KeyCode::A) {
-                hurtbox.vel = vec2(-MOVEMENT_SPEED, 0.);
+                velocity.0 = vec2(-self.movement_speed, 0.);
                 self.facing = Facing::Left;
             }
             else if keyboard_input.pressed(KeyCode::D) {
-                hurtbox.vel = vec2(MOVEMENT_SPEED, 0.);
+                velocity.0 = vec2(self.movement_speed, 0.);
                 self.facing = Facing::Right;
             }
 
@@ -222,27 +292,84 @@ impl Player {
 
     pub fn slash_action(
         &mut self,
+        entity: Entity,
         angle: f32,
+        combo: usize,
+        weapon: &Weapon,
         keyboard_input: &Input<KeyCode>,
         mouse: &MouseState,
-        mouse_button_input: &Input<MouseButton>,
-        hurtbox: &mut Hurtbox,
+        velocity: &mut Velocity,
         transform: &mut Transform,
         hitbox: &mut EventWriter<HitBoxEvent>,
+        attack_ids: &mut AttackIds,
     ) {
-        if self.frame < 10 {
-            hurtbox.vel = vec2(angle.cos() * 8.0, angle.sin() * 8.0);
+        let attack = weapon.attack(combo);
+        let rehit = attack.rehit_frames.map_or(false, |frames| self.frame % frames as u64 == 0);
+        let id = match self.attack {
+            Some(id) if !rehit => id,
+            _ => attack_ids.next(),
+        };
+        self.attack = Some(id);
+        if self.frame < attack.active_frames as u64 {
+            velocity.0 = vec2(angle.cos(), angle.sin()) * attack.lunge_speed;
             hitbox.send(HitBoxEvent {
-                position: transform.translation.truncate() + Vec2::new(angle.cos(), angle.sin()) * 70.0,
-                size: Vec2::new(60.0, 60.0),
-                damage: 2,
-                knockback: 30.0,
-                can_hit: CanHitTeam::Enemy,
+                position: transform.translation.truncate() + Vec2::new(angle.cos(), angle.sin()) * attack.hitbox_offset,
+                size: attack.hitbox_size(),
+                damage: attack.damage + self.slash_damage,
+                damage_type: attack.damage_type,
+                knockback: attack.knockback,
+                can_hit: CanHitTeam::NotAllied(Team::Player),
+                attack: id,
+                owner: Some(entity),
+                target: None,
+                effects: attack.effects.clone(),
+            });
+        }
+        if weapon.has_followup(combo) && self.frame >= attack.cancel_frame as u64 && self.buffered(BufferedInput::Slash) {
+            if let Some(angle) = mouse.angle_from_location_to_mouse(transform.translation.truncate()) {
+                self.start_slash(angle, combo + 1, velocity);
+                return;
+            }
+        }
+        if self.frame >= attack.total_frames() as u64 {
+            self.set_action(PlayerAction::Idle);
+            self.idle_action(keyboard_input, mouse, velocity, transform);
+        }
+    }
+
+    pub fn shoot_action(
+        &mut self,
+        entity: Entity,
+        angle: f32,
+        keyboard_input: &Input<KeyCode>,
+        mouse: &MouseState,
+        velocity: &mut Velocity,
+        transform: &mut Transform,
+        projectiles: &mut EventWriter<SpawnProjectileEvent>,
+    ) {
+        if self.frame == 0 {
+            let direction = Vec2::new(angle.cos(), angle.sin());
+            projectiles.send(SpawnProjectileEvent {
+                position: transform.translation.truncate() + direction * 30.0,
+                color: Color::rgb(0.4, 0.8, 1.0),
+                vel: direction * BOLT_SPEED,
+                projectile: Projectile {
+                    size: Vec2::new(16.0, 16.0),
+                    lifetime: BOLT_LIFETIME,
+                    pierce: 0,
+                    damage: BOLT_DAMAGE + self.slash_damage / 2,
+                    damage_type: DamageType::Holy,
+                    knockback: 10.0,
+                    can_hit: CanHitTeam::NotAllied(Team::Player),
+                    owner: Some(entity),
+                    effects: vec![],
+                    hits: 0,
+                },
             });
         }
-        if self.frame > 17 {
+        if self.frame >= SHOOT_DURATION as u64 {
             self.set_action(PlayerAction::Idle);
-            self.idle_action(&keyboard_input, mouse, mouse_button_input, hurtbox, transform);
+            self.idle_action(keyboard_input, mouse, velocity, transform);
         }
     }
 
@@ -251,21 +378,21 @@ impl Player {
         angle: f32,
         keyboard_input: &Input<KeyCode>,
         mouse: &MouseState,
-        mouse_button_input: &Input<MouseButton>,
         hurtbox: &mut Hurtbox,
+        velocity: &mut Velocity,
         transform: &mut Transform,
     ) {
         self.frame_since_last_cooldown = 0;
         hurtbox.invincible = true;
 
         if self.frame < 4 {
-            hurtbox.vel = Vec2::new(angle.cos(), angle.sin()) * DASH_SPEED;
+            velocity.0 = Vec2::new(angle.cos(), angle.sin()) * DASH_SPEED;
         }
 
         if self.frame > DASH_DURATION as u64{
             hurtbox.invincible = false;
             self.set_action(PlayerAction::Idle);
-            self.idle_action(&keyboard_input, mouse, mouse_button_input, hurtbox, transform);
+            self.idle_action(keyboard_input, mouse, velocity, transform);
         }
     }
 
@@ -273,14 +400,14 @@ impl Player {
         &mut self,
         keyboard_input: &Input<KeyCode>,
         mouse: &MouseState,
-        mouse_button_input: &Input<MouseButton>,
         hurtbox: &mut Hurtbox,
+        velocity: &mut Velocity,
         transform: &mut Transform,
     ) {
         if self.frame > DAMAGED_INVINCIBILITY_FRAMES as u64 {
             hurtbox.invincible = false;
             self.set_action(PlayerAction::Idle);
-            self.idle_action(&keyboard_input, mouse, mouse_button_input, hurtbox, transform);
+            self.idle_action(keyboard_input, mouse, velocity, transform);
         }
     }
 }
@@ -289,31 +416,51 @@ pub fn player_system(
     keyboard_input: Res<Input<KeyCode>>,
     mouse_button_input: Res<Input<MouseButton>>,
     mouse: Res<MouseState>,
-    mut query: Query<(&mut Player, &mut Hurtbox, &mut Transform, &mut TextureAtlasSprite)>,
+    interface: Res<Interface>,
+    weapons: Res<Weapons>,
+    mut query: Query<(Entity, &mut Player, &mut Hurtbox, &mut Velocity, &Inventory, &StatusEffects, &mut Transform, &mut TextureAtlasSprite)>,
     mut hitbox: EventWriter<HitBoxEvent>,
+    mut projectiles: EventWriter<SpawnProjectileEvent>,
+    mut attack_ids: ResMut<AttackIds>,
 ) {
-    if let Ok((mut player, mut hurtbox, mut transform, mut sprite)) = query.single_mut() {
+    if *interface != Interface::None {
+        return;
+    }
+
+    if let Ok((entity, mut player, mut hurtbox, mut velocity, inventory, status, mut transform, mut sprite)) = query.single_mut() {
+        if status.is_stunned() {
+            return;
+        }
+
         if hurtbox.is_hit {
             player.set_action(PlayerAction::Damaged);
+            hurtbox.invincible = true;
             hurtbox.is_hit = false;
         }
+        player.buffer_input(&keyboard_input, &mouse_button_input);
 
         match player.action {
             PlayerAction::Idle => {
                 // TODO: lets create a context struct which holds all the args so we dont have argument soup
-                player.idle_action(&keyboard_input, &mouse, &mouse_button_input, &mut hurtbox, &mut transform);
+                player.idle_action(&keyboard_input, &mouse, &mut velocity, &mut transform);
             }
             PlayerAction::Walk => {
-                player.walk_action(&keyboard_input, &mouse, &mouse_button_input, &mut hurtbox, &mut transform);
+                player.walk_action(&keyboard_input, &mouse, &mut velocity, &mut transform);
+            }
+            PlayerAction::Slash { angle, combo } => {
+                match weapons.get(inventory.equipped()) {
+                    Some(weapon) => player.slash_action(entity, angle, combo, weapon, &keyboard_input, &mouse, &mut velocity, &mut transform, &mut hitbox, &mut attack_ids),
+                    None => player.set_action(PlayerAction::Idle),
+                }
             }
-            PlayerAction::Slash { angle } => {
-                player.slash_action(angle, &keyboard_input, &mouse, &mouse_button_input, &mut hurtbox, &mut transform, &mut hitbox);
+            PlayerAction::Shoot { angle } => {
+                player.shoot_action(entity, angle, &keyboard_input, &mouse, &mut velocity, &mut transform, &mut projectiles);
             }
             PlayerAction::Dash { angle } => {
-                player.dash_action(angle, &keyboard_input, &mouse, &mouse_button_input, &mut hurtbox, &mut transform);
+                player.dash_action(angle, &keyboard_input, &mouse, &mut hurtbox, &mut velocity, &mut transform);
             },
             PlayerAction::Damaged => {
-                player.damaged_action(&keyboard_input, &mouse, &mouse_button_input, &mut hurtbox, &mut transform);
+                player.damaged_action(&keyboard_input, &mouse, &mut hurtbox, &mut velocity, &mut transform);
             }
         }
 
@@ -341,6 +488,10 @@ pub fn player_system(
                 set_sprite_index(&mut sprite, 0, 0);
                 set_sprite_flip_from_facing(&mut sprite, facing);
             }
+            (PlayerAction::Shoot { .. }, facing) => {
+                set_sprite_index(&mut sprite, 0, 0);
+                set_sprite_flip_from_facing(&mut sprite, facing);
+            }
             (PlayerAction::Dash { .. }, facing) => {
                 sprite.index = 0;
                 set_sprite_index(&mut sprite, 0, 0);
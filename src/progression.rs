@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::Hurtbox;
+use crate::player::Player;
+
+// loaded from assets/data/progression.ron
+#[derive(Deserialize)]
+pub struct Progression {
+    pub base_exp: u64, // exp needed to go from level 1 to level 2
+    pub exp_growth: f32, // every level needs this many times more exp than the last
+    pub max_health: u64, // stat gains per level
+    pub slash_damage: u64,
+    pub movement_speed: f32,
+}
+
+impl Progression {
+    pub fn exp_to_next_level(&self, level: u64) -> u64 {
+        let exp = (self.base_exp as f32 * self.exp_growth.powi(level as i32 - 1)).round() as u64;
+        exp.max(1)
+    }
+}
+
+pub struct LevelUpEvent {
+    pub level: u64,
+}
+
+pub fn level_up_system(
+    progression: Res<Progression>,
+    mut player: Query<(&mut Player, &mut Hurtbox)>,
+    mut level_ups: EventWriter<LevelUpEvent>,
+) {
+    if let Ok((mut player, mut hurtbox)) = player.single_mut() {
+        while player.exp >= progression.exp_to_next_level(player.level) {
+            player.exp -= progression.exp_to_next_level(player.level);
+            player.level += 1;
+
+            hurtbox.max_health += progression.max_health;
+            hurtbox.health += progression.max_health;
+            player.slash_damage += progression.slash_damage;
+            player.movement_speed += progression.movement_speed;
+
+            level_ups.send(LevelUpEvent { level: player.level });
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy::sprite::collide_aabb;
+
+use crate::collision::{AttackId, AttackIds, CanHitTeam, DamageType, Factions, HitBoxEvent, Hurtbox, Wall};
+use crate::physics::{Friction, Velocity};
+use crate::status::StatusPayload;
+use crate::Interface;
+
+#[derive(Clone)]
+pub struct Projectile {
+    pub size: Vec2,
+    pub lifetime: u32, // frames until it despawns on its own
+    pub pierce: u32, // how many targets it passes through before despawning
+    pub damage: u64,
+    pub damage_type: DamageType,
+    pub knockback: f32,
+    pub can_hit: CanHitTeam,
+    pub owner: Option<Entity>,
+    pub effects: Vec<StatusPayload>,
+    pub hits: u32, // targets hit so far
+}
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    projectile: Projectile,
+    velocity: Velocity,
+    friction: Friction,
+    #[bundle]
+    sprite: SpriteBundle
+}
+impl ProjectileBundle {
+    pub fn new(materials: &mut Assets<ColorMaterial>, position: Vec2, vel: Vec2, color: Color, projectile: Projectile) -> Self {
+        let angle = Vec2::new(1.0, 0.0).angle_between(vel);
+        Self {
+            sprite: SpriteBundle {
+                material: materials.add(color.into()),
+                transform: Transform {
+                    translation: position.extend(0.5),
+                    rotation: Quat::from_rotation_z(angle),
+                    ..Default::default()
+                },
+                sprite: Sprite::new(projectile.size),
+                ..Default::default()
+            },
+            projectile,
+            velocity: Velocity(vel),
+            friction: Friction(1.0),
+        }
+    }
+}
+
+// lets any system fire a projectile without needing access to the materials
+pub struct SpawnProjectileEvent {
+    pub position: Vec2,
+    pub vel: Vec2,
+    pub color: Color,
+    pub projectile: Projectile,
+}
+
+pub fn spawn_projectiles(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut attack_ids: ResMut<AttackIds>,
+    mut events: EventReader<SpawnProjectileEvent>,
+) {
+    for event in events.iter() {
+        commands
+            .spawn_bundle(ProjectileBundle::new(&mut materials, event.position, event.vel, event.color, event.projectile.clone()))
+            .insert(attack_ids.next());
+    }
+}
+
+pub fn projectile_system(
+    mut commands: Commands,
+    interface: Res<Interface>,
+    mut projectiles: Query<(Entity, &mut Projectile, &AttackId, &Transform)>,
+    targets: Query<(Entity, &Hurtbox, &Transform), Without<Projectile>>,
+    walls: Query<(&Wall, &Transform), Without<Projectile>>,
+    mut hitbox: EventWriter<HitBoxEvent>,
+    factions: Res<Factions>,
+) {
+    if *interface != Interface::None {
+        return;
+    }
+
+    for (entity, mut projectile, attack, transform) in projectiles.iter_mut() {
+        projectile.lifetime = projectile.lifetime.saturating_sub(1);
+
+        let hit_wall = walls.iter().any(|(wall, wall_transform)| {
+            collide_aabb::collide(transform.translation, projectile.size, wall_transform.translation, wall.size).is_some()
+        });
+        if hit_wall || projectile.lifetime == 0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        for (target, hurtbox, target_transform) in targets.iter() {
+            if projectile.owner != Some(target) && projectile.can_hit.can_hit(hurtbox.team, &factions) &&
+                !hurtbox.invincible && !hurtbox.was_hit_by(*attack) &&
+                collide_aabb::collide(transform.translation, projectile.size, target_transform.translation, hurtbox.size).is_some()
+            {
+                hitbox.send(HitBoxEvent {
+                    position: transform.translation.truncate(),
+                    size: projectile.size,
+                    damage: projectile.damage,
+                    damage_type: projectile.damage_type,
+                    knockback: projectile.knockback,
+                    can_hit: projectile.can_hit.clone(),
+                    attack: *attack,
+                    owner: projectile.owner,
+                    // the hitbox can overlap a whole crowd, only the target counts against the pierce
+                    target: Some(target),
+                    effects: projectile.effects.clone(),
+                });
+                projectile.hits += 1;
+                if projectile.hits > projectile.pierce {
+                    commands.entity(entity).despawn();
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::{Factions, Hurtbox, Team};
+use crate::dialogue::{DialogueAction, DialogueEvent};
+use crate::menu::{set_menu_text, spawn_menu, MenuText, MENU_KEYS};
+use crate::player::Player;
+use crate::weapon::Inventory;
+use crate::{Interface, DASH_DURATION, SHOP_RANGE};
+
+#[derive(Deserialize)]
+pub struct ShopItem {
+    pub name: String,
+    pub price: u64,
+    pub effect: ShopEffect,
+}
+
+#[derive(Deserialize, Clone)]
+pub enum ShopEffect {
+    Heal(u64),
+    MaxHealth(u64),
+    Damage(u64),
+    DashCooldown(u32), // frames taken off the dash cooldown
+    Weapon(String), // id of a weapon in weapons.ron
+}
+
+impl ShopEffect {
+    // weapons can only be bought once
+    pub fn owned(&self, inventory: &Inventory) -> bool {
+        match self {
+            ShopEffect::Weapon(weapon) => inventory.weapons.iter().any(|owned| owned == weapon),
+            _ => false,
+        }
+    }
+
+    pub fn apply(&self, player: &mut Player, hurtbox: &mut Hurtbox, inventory: &mut Inventory) {
+        match *self {
+            ShopEffect::Heal(amount) => {
+                hurtbox.health = (hurtbox.health + amount).min(hurtbox.max_health);
+            }
+            ShopEffect::MaxHealth(amount) => {
+                hurtbox.max_health += amount;
+                hurtbox.health += amount;
+            }
+            ShopEffect::Damage(amount) => {
+                player.slash_damage += amount;
+            }
+            ShopEffect::DashCooldown(frames) => {
+                player.dash_cooldown = player.dash_cooldown.saturating_sub(frames).max(DASH_DURATION);
+            }
+            ShopEffect::Weapon(ref weapon) => {
+                inventory.add_weapon(weapon);
+            }
+        }
+    }
+}
+
+// loaded from assets/data/shop.ron, keyed by the name of the stock
+pub struct ShopItems(pub HashMap<String, Vec<ShopItem>>);
+
+impl ShopItems {
+    pub fn find(&self, name: &str) -> Option<&ShopItem> {
+        self.0.values().flatten().find(|item| item.name == name)
+    }
+}
+
+// anyone the player can trade with, `stock` is a list in shop.ron
+pub struct Merchant {
+    pub stock: String,
+}
+
+impl Merchant {
+    pub fn new(stock: &str) -> Self {
+        Merchant { stock: stock.to_string() }
+    }
+}
+
+impl ShopItems {
+    // ids of every weapon for sale
+    pub fn weapons(&self) -> impl Iterator<Item = &str> {
+        self.0.values().flatten().filter_map(|item| match &item.effect {
+            ShopEffect::Weapon(id) => Some(id.as_str()),
+            _ => None,
+        })
+    }
+}
+
+pub struct ShopMenu;
+
+#[derive(Default)]
+pub struct Shop {
+    message: String,
+    stock: String,
+}
+
+pub fn shop_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    items: Res<ShopItems>,
+    mut interface: ResMut<Interface>,
+    mut shop: ResMut<Shop>,
+    mut dialogue_events: EventReader<DialogueEvent>,
+    mut player: Query<(&mut Player, &mut Hurtbox, &mut Inventory, &Transform)>,
+    factions: Res<Factions>,
+    merchants: Query<(&Merchant, Option<&Hurtbox>, &Transform), Without<Player>>,
+    menus: Query<Entity, With<ShopMenu>>,
+    mut text: Query<&mut Text, With<MenuText>>,
+) {
+    let from_dialogue = dialogue_events.iter().find_map(|event| match &event.action {
+        DialogueAction::OpenShop(stock) => Some(stock.clone()),
+        _ => None,
+    });
+
+    if let Ok((mut player, mut hurtbox, mut inventory, player_transform)) = player.single_mut() {
+        match *interface {
+            Interface::None => {
+                let in_range = merchants.iter()
+                    .find(|(_, hurtbox, transform)| {
+                        !hurtbox.map_or(false, |hurtbox| factions.is_hostile(hurtbox.team, Team::Player)) &&
+                            (player_transform.translation - transform.translation).truncate().length() < SHOP_RANGE
+                    })
+                    .map(|(merchant, _, _)| merchant.stock.clone());
+                let stock = match from_dialogue {
+                    Some(stock) => Some(stock),
+                    None if keyboard_input.just_pressed(KeyCode::E) => in_range,
+                    None => None,
+                };
+                if let Some(stock) = stock {
+                    *interface = Interface::Shop;
+                    shop.message = String::new();
+                    shop.stock = stock;
+                    spawn_menu(&mut commands, &asset_server, &mut materials, ShopMenu);
+                }
+            }
+            Interface::Shop => {
+                let stock = items.0.get(&shop.stock).map_or(&[][..], |stock| &stock[..]);
+                for (item, key) in stock.iter().zip(MENU_KEYS.iter()) {
+                    if keyboard_input.just_pressed(*key) {
+                        if item.effect.owned(&inventory) {
+                            shop.message = format!("You already own {}.", item.name);
+                        } else if player.money >= item.price {
+                            player.money -= item.price;
+                            item.effect.apply(&mut player, &mut hurtbox, &mut inventory);
+                            shop.message = format!("Bought {}.", item.name);
+                        } else {
+                            shop.message = format!("You can't afford {}.", item.name);
+                        }
+                    }
+                }
+
+                let mut value = format!("Shop - Money: {}\n\n", player.money);
+                for (i, item) in stock.iter().take(MENU_KEYS.len()).enumerate() {
+                    value += &format!("{}. {} - {}\n", i + 1, item.name, item.price);
+                }
+                value += &format!("\n{}\nPress E to leave.", shop.message);
+                set_menu_text(&mut text, value);
+
+                if keyboard_input.just_pressed(KeyCode::E) || keyboard_input.just_pressed(KeyCode::Escape) {
+                    *interface = Interface::None;
+                    for entity in menus.iter() {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
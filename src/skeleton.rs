@@ -1,89 +1,81 @@
 use bevy::prelude::*;
-use crate::player::Player;
-use crate::collision::{Hurtbox, Team, CanHitTeam, HitBoxEvent};
+use crate::behavior::Brain;
+use crate::collision::{Hurtbox, Team, DamageType, Resistances};
+use crate::difficulty::Difficulty;
+use crate::loot::Reward;
+use crate::physics::{Body, PhysicsBundle};
+use crate::status::StatusEffects;
 
 #[derive(Bundle)]
 pub struct SkeletonBundle {
     skeleton: Skeleton,
+    brain: Brain,
     hurtbox: Hurtbox,
+    reward: Reward,
+    status: StatusEffects,
+    resistances: Resistances,
+    body: Body,
+    #[bundle]
+    physics: PhysicsBundle,
     #[bundle]
     sprite: SpriteBundle
 }
 impl SkeletonBundle {
-    pub fn new(materials: &mut Assets<ColorMaterial>, position: Vec2) -> Self {
+    pub fn new(materials: &mut Assets<ColorMaterial>, position: Vec2, difficulty: &Difficulty) -> Self {
+        Self::with_kind(materials, position, difficulty, SkeletonKind::Melee)
+    }
+
+    pub fn archer(materials: &mut Assets<ColorMaterial>, position: Vec2, difficulty: &Difficulty) -> Self {
+        Self::with_kind(materials, position, difficulty, SkeletonKind::Archer)
+    }
+
+    fn with_kind(materials: &mut Assets<ColorMaterial>, position: Vec2, difficulty: &Difficulty, kind: SkeletonKind) -> Self {
+        // the reward table and the behavior tree share the name
+        let (health, mass, name) = match kind {
+            SkeletonKind::Melee => (10, 1.0, "skeleton"),
+            SkeletonKind::Archer => (6, 0.8, "skeleton_archer"),
+        };
+        let health = difficulty.health(health);
         Self {
-            skeleton: Skeleton {
-                action: SkeletonAction::Walk,
-                frame: 0,
-            },
             hurtbox: Hurtbox {
                 size: Vec2::new(60.0, 100.0),
-                health: 10,
+                health,
+                max_health: health,
                 team: Team::Enemy,
                 is_hit: false,
                 invincible: false,
-                vel: Vec2::new(0.0, 0.0)
+                last_hit_by: None,
+                hit_by: vec![],
             },
+            physics: PhysicsBundle::new(mass),
+            body: Body { size: Vec2::new(60.0, 100.0), push: 0.5 },
+            reward: Reward::new(name),
+            status: StatusEffects::default(),
+            resistances: Resistances::new(&[(DamageType::Holy, 2.0)]),
             sprite: SpriteBundle {
-                material: materials.add(Color::rgb(0.5, 0.5, 1.0).into()),
+                material: materials.add(kind.color().into()),
                 transform: Transform::from_translation(position.extend(0.0)),
                 sprite: Sprite::new(Vec2::new(60.0, 100.0)),
                 ..Default::default()
-            }
+            },
+            brain: Brain::new(name, kind.color(), difficulty),
+            skeleton: Skeleton,
         }
     }
 }
 
-pub enum SkeletonAction {
-    Walk,
-    Damaged
+pub enum SkeletonKind {
+    Melee,
+    Archer,
 }
 
-pub struct Skeleton {
-    action: SkeletonAction,
-    frame: u64,
-}
-
-impl Skeleton {
-    pub fn set_action(&mut self, action: SkeletonAction) {
-        self.frame = 0;
-        self.action = action;
-    }
-}
-
-pub fn skeleton_system(
-    mut player_query: Query<(&mut Player, &Transform)>,
-    mut enemy_query: Query<(&mut Skeleton, &mut Hurtbox, &mut Transform), Without<Player>>,
-    mut hitbox: EventWriter<HitBoxEvent>,
-) {
-    if let Ok((_player, player_transform)) = player_query.single_mut() {
-        for (mut skeleton, mut hurtbox, transform) in enemy_query.iter_mut() {
-            if hurtbox.is_hit {
-                skeleton.set_action(SkeletonAction::Damaged);
-                hurtbox.is_hit = false;
-            }
-
-            match &skeleton.action {
-                SkeletonAction::Walk => {
-                    hurtbox.invincible = false;
-                    let difference = player_transform.translation - transform.translation;
-                    hurtbox.vel = difference.truncate().normalize() * 1.5;
-                    hitbox.send(HitBoxEvent {
-                        position: transform.translation.truncate(),
-                        size: Vec2::new(50.0, 90.0),
-                        damage: 2,
-                        knockback: 30.0,
-                        can_hit: CanHitTeam::Player,
-                    });
-                }
-                SkeletonAction::Damaged => {
-                    if skeleton.frame > 15 {
-                        skeleton.set_action(SkeletonAction::Walk);
-                    }
-                }
-            }
-
-            skeleton.frame += 1;
+impl SkeletonKind {
+    fn color(&self) -> Color {
+        match self {
+            SkeletonKind::Melee => Color::rgb(0.5, 0.5, 1.0),
+            SkeletonKind::Archer => Color::rgb(0.5, 0.9, 0.6),
         }
     }
 }
+
+pub struct Skeleton;
@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::{DamageType, Hurtbox, Resistances};
+use crate::Interface;
+
+const POISON_TICK_FRAMES: u32 = 30;
+const POISON_MAX_STACKS: u32 = 5;
+const BURN_TICK_FRAMES: u32 = 20;
+const ICON_SIZE: f32 = 12.0;
+
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum StatusKind {
+    Poison, // `potency` damage per stack every tick, stacks up to POISON_MAX_STACKS
+    Burn, // `potency` damage every tick, reapplying only refreshes it
+    Slow, // `potency` percent slower, the strongest slow wins
+    Stun, // can't act, the longest stun wins
+}
+
+impl StatusKind {
+    fn color(&self) -> Color {
+        match self {
+            StatusKind::Poison => Color::rgb(0.3, 0.9, 0.2),
+            StatusKind::Burn => Color::rgb(1.0, 0.5, 0.0),
+            StatusKind::Slow => Color::rgb(0.4, 0.7, 1.0),
+            StatusKind::Stun => Color::rgb(1.0, 1.0, 0.2),
+        }
+    }
+
+    fn damage_type(&self) -> DamageType {
+        match self {
+            StatusKind::Burn => DamageType::Fire,
+            _ => DamageType::Physical,
+        }
+    }
+}
+
+// carried by hitboxes, applied to whatever they hit
+#[derive(Deserialize, Clone, Copy)]
+pub struct StatusPayload {
+    pub kind: StatusKind,
+    pub duration: u32, // in fixed steps
+    pub potency: u32,
+}
+
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub remaining: u32,
+    pub potency: u32,
+    pub stacks: u32,
+    elapsed: u32,
+}
+
+#[derive(Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+    icons: Vec<(StatusKind, Entity)>,
+}
+
+impl StatusEffects {
+    pub fn apply(&mut self, payload: &StatusPayload) {
+        if let Some(effect) = self.effects.iter_mut().find(|effect| effect.kind == payload.kind) {
+            match payload.kind {
+                StatusKind::Poison => {
+                    effect.stacks = (effect.stacks + 1).min(POISON_MAX_STACKS);
+                    effect.remaining = payload.duration;
+                }
+                StatusKind::Burn => {
+                    effect.remaining = payload.duration;
+                }
+                StatusKind::Slow => {
+                    effect.potency = effect.potency.max(payload.potency);
+                    effect.remaining = effect.remaining.max(payload.duration);
+                }
+                StatusKind::Stun => {
+                    effect.remaining = effect.remaining.max(payload.duration);
+                }
+            }
+        } else {
+            self.effects.push(StatusEffect {
+                kind: payload.kind,
+                remaining: payload.duration,
+                potency: payload.potency,
+                stacks: 1,
+                elapsed: 0,
+            });
+        }
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.effects.iter().any(|effect| effect.kind == StatusKind::Stun)
+    }
+
+    pub fn speed_multiplier(&self) -> f32 {
+        self.effects.iter()
+            .filter(|effect| effect.kind == StatusKind::Slow)
+            .map(|effect| 1.0 - effect.potency.min(100) as f32 / 100.0)
+            .fold(1.0, f32::min)
+    }
+}
+
+pub fn is_stunned(effects: Option<&StatusEffects>) -> bool {
+    effects.map_or(false, |effects| effects.is_stunned())
+}
+
+pub fn status_system(
+    interface: Res<Interface>,
+    mut entities: Query<(&mut StatusEffects, &mut Hurtbox, Option<&Resistances>)>,
+) {
+    if *interface != Interface::None {
+        return;
+    }
+
+    for (mut status, mut hurtbox, resistances) in entities.iter_mut() {
+        for effect in status.effects.iter_mut() {
+            effect.elapsed += 1;
+            let damage = match effect.kind {
+                StatusKind::Poison if effect.elapsed % POISON_TICK_FRAMES == 0 => effect.potency * effect.stacks,
+                StatusKind::Burn if effect.elapsed % BURN_TICK_FRAMES == 0 => effect.potency,
+                _ => 0,
+            };
+            // ticks still count down while invincible, they just don't hurt
+            let damage = if hurtbox.invincible { 0 } else { damage };
+            let damage = Resistances::resolve(resistances, damage as u64, effect.kind.damage_type());
+            hurtbox.health = hurtbox.health.saturating_sub(damage);
+            effect.remaining = effect.remaining.saturating_sub(1);
+        }
+        status.effects.retain(|effect| effect.remaining > 0);
+    }
+}
+
+// a small coloured square above an entity for each status effect on it
+pub struct StatusIcon {
+    owner: Entity,
+}
+
+pub fn status_icon_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut owners: Query<(Entity, &mut StatusEffects, &Hurtbox, &Transform), Without<StatusIcon>>,
+    mut icons: Query<(Entity, &StatusIcon, &mut Transform)>,
+) {
+    for (owner, mut status, _, _) in owners.iter_mut() {
+        let status = &mut *status;
+        for effect in status.effects.iter() {
+            if !status.icons.iter().any(|(kind, _)| *kind == effect.kind) {
+                let icon = commands.spawn_bundle(SpriteBundle {
+                    material: materials.add(effect.kind.color().into()),
+                    sprite: Sprite::new(Vec2::splat(ICON_SIZE)),
+                    ..Default::default()
+                }).insert(StatusIcon { owner }).id();
+                status.icons.push((effect.kind, icon));
+            }
+        }
+
+        let effects = &status.effects;
+        status.icons.retain(|(kind, icon)| {
+            let active = effects.iter().any(|effect| effect.kind == *kind);
+            if !active {
+                commands.entity(*icon).despawn();
+            }
+            active
+        });
+    }
+
+    for (icon, status_icon, mut transform) in icons.iter_mut() {
+        if let Ok((_, status, hurtbox, owner_transform)) = owners.get_mut(status_icon.owner) {
+            let index = status.icons.iter().position(|(_, entity)| *entity == icon).unwrap_or(0);
+            let offset = Vec2::new(
+                (index as f32 - (status.icons.len() as f32 - 1.0) / 2.0) * (ICON_SIZE + 4.0),
+                hurtbox.size.y / 2.0 + ICON_SIZE,
+            );
+            transform.translation = (owner_transform.translation.truncate() + offset).extend(1.0);
+        } else {
+            commands.entity(icon).despawn();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use bevy::prelude::*;
+use bevy_prototype_debug_lines::*;
+
+// shows where an attack is about to land, sent every frame of its windup
+pub struct TelegraphEvent {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub rotation: f32,
+    pub progress: f32, // how far through the windup, from 0 to 1
+    pub frames_left: u64,
+}
+
+// the fill sprites only live for a single frame
+pub struct Telegraph;
+
+pub fn telegraph_system(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut fill: Local<Option<Handle<ColorMaterial>>>,
+    mut events: EventReader<TelegraphEvent>,
+    mut lines: ResMut<DebugLines>,
+    old: Query<Entity, With<Telegraph>>,
+) {
+    for entity in old.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let fill = fill.get_or_insert_with(|| materials.add(Color::rgba(1.0, 0.1, 0.0, 0.35).into())).clone();
+    for event in events.iter() {
+        let rotation = Quat::from_rotation_z(event.rotation);
+
+        // the fill grows out from the middle until it covers the whole hitbox
+        commands.spawn_bundle(SpriteBundle {
+            material: fill.clone(),
+            transform: Transform {
+                translation: event.position.extend(0.2),
+                rotation,
+                ..Default::default()
+            },
+            sprite: Sprite::new(event.size * event.progress),
+            ..Default::default()
+        }).insert(Telegraph);
+
+        // and the outline flashes, faster as the attack gets close
+        let period = if event.frames_left < 20 { 2 } else { 6 };
+        if (event.frames_left / period) % 2 == 0 {
+            let half = event.size / 2.0;
+            let corners: Vec<Vec3> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                .iter()
+                .map(|(x, y)| event.position.extend(0.2) + rotation * Vec3::new(half.x * x, half.y * y, 0.0))
+                .collect();
+            for i in 0..corners.len() {
+                lines.line_colored(corners[i], corners[(i + 1) % corners.len()], 0.0, Color::RED);
+            }
+        }
+    }
+}
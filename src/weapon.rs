@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::collision::DamageType;
+use crate::data;
+use crate::menu::{set_menu_text, spawn_menu, MenuText, MENU_KEYS};
+use crate::player::Player;
+use crate::shop::ShopItems;
+use crate::status::StatusPayload;
+use crate::{Interface, STARTING_WEAPON};
+
+#[derive(Deserialize)]
+pub struct Attack {
+    pub hitbox_size: (f32, f32),
+    pub hitbox_offset: f32, // how far in front of the player the hitbox is
+    pub damage: u64,
+    #[serde(default)]
+    pub damage_type: DamageType,
+    pub knockback: f32,
+    pub active_frames: u32,
+    pub recovery_frames: u32, // frames after the hitbox is gone before u can act again
+    pub lunge_speed: f32,
+    pub cancel_frame: u32, // from this frame on a queued slash chains into the next attack of the combo
+    #[serde(default)]
+    pub effects: Vec<StatusPayload>,
+    #[serde(default)]
+    pub rehit_frames: Option<u32>, // multi-hit attacks can hit the same target again after this many frames
+}
+
+impl Attack {
+    pub fn hitbox_size(&self) -> Vec2 {
+        Vec2::new(self.hitbox_size.0, self.hitbox_size.1)
+    }
+
+    pub fn total_frames(&self) -> u32 {
+        self.active_frames + self.recovery_frames
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Weapon {
+    pub name: String,
+    pub combo: Vec<Attack>,
+}
+
+impl Weapon {
+    pub fn attack(&self, combo: usize) -> &Attack {
+        &self.combo[combo.min(self.combo.len() - 1)]
+    }
+
+    pub fn has_followup(&self, combo: usize) -> bool {
+        combo + 1 < self.combo.len()
+    }
+}
+
+// loaded from assets/data/weapons.ron, keyed by weapon id
+pub struct Weapons(pub HashMap<String, Weapon>);
+
+impl Weapons {
+    // like data::load, panics if a weapon can't be swung or one the game hands out is missing from the file
+    pub fn load(path: &str, shop_items: &ShopItems) -> Self {
+        let weapons = Weapons(data::load(path));
+        // `Weapon::attack` needs at least one attack to fall back on
+        for (id, weapon) in weapons.0.iter() {
+            if weapon.combo.is_empty() {
+                panic!("weapon {} in {} has an empty combo", id, path);
+            }
+        }
+        for id in std::iter::once(STARTING_WEAPON).chain(shop_items.weapons()) {
+            if weapons.get(id).is_none() {
+                panic!("no weapon called {} in {}", id, path);
+            }
+        }
+        weapons
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Weapon> {
+        self.0.get(id)
+    }
+}
+
+pub struct Inventory {
+    pub weapons: Vec<String>,
+    pub equipped: usize,
+}
+
+impl Inventory {
+    pub fn new(weapon: &str) -> Self {
+        Inventory {
+            weapons: vec![weapon.to_string()],
+            equipped: 0,
+        }
+    }
+
+    pub fn equipped(&self) -> &str {
+        &self.weapons[self.equipped]
+    }
+
+    pub fn add_weapon(&mut self, weapon: &str) {
+        if !self.weapons.iter().any(|owned| owned == weapon) {
+            self.weapons.push(weapon.to_string());
+        }
+    }
+}
+
+pub struct EquipmentMenu;
+
+pub fn equipment_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    weapons: Res<Weapons>,
+    mut interface: ResMut<Interface>,
+    mut player: Query<(&Player, &mut Inventory)>,
+    menus: Query<Entity, With<EquipmentMenu>>,
+    mut text: Query<&mut Text, With<MenuText>>,
+) {
+    if let Ok((player, mut inventory)) = player.single_mut() {
+        match *interface {
+            Interface::None => {
+                if keyboard_input.just_pressed(KeyCode::I) {
+                    *interface = Interface::Equipment;
+                    spawn_menu(&mut commands, &asset_server, &mut materials, EquipmentMenu);
+                }
+            }
+            Interface::Equipment => {
+                for (i, key) in MENU_KEYS.iter().enumerate().take(inventory.weapons.len()) {
+                    if keyboard_input.just_pressed(*key) {
+                        inventory.equipped = i;
+                    }
+                }
+
+                let mut value = String::from("Equipment\n\n");
+                for (i, id) in inventory.weapons.iter().take(MENU_KEYS.len()).enumerate() {
+                    let weapon = match weapons.get(id) {
+                        Some(weapon) => weapon,
+                        None => continue,
+                    };
+                    value += &format!(
+                        "{}{}. {} - damage {}, reach {}, {} hit combo\n",
+                        if i == inventory.equipped { "> " } else { "" },
+                        i + 1,
+                        weapon.name,
+                        weapon.attack(0).damage + player.slash_damage,
+                        weapon.attack(0).hitbox_offset,
+                        weapon.combo.len(),
+                    );
+                }
+                value += "\nPress I to leave.";
+                set_menu_text(&mut text, value);
+
+                if keyboard_input.just_pressed(KeyCode::I) || keyboard_input.just_pressed(KeyCode::Escape) {
+                    *interface = Interface::None;
+                    for entity in menus.iter() {
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}